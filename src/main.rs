@@ -1,3 +1,5 @@
+extern crate rand;
+extern crate rustc_serialize;
 extern crate sdl2;
 extern crate sdl2_image;
 extern crate sdl2_ttf;
@@ -5,9 +7,94 @@ extern crate sdl2_ttf;
 mod phi;
 mod views;
 
-fn main() {
-    ::phi::spawn("ArcadeRS Shooter", |phi| {
-        Box::new(::views::main_menu::MainMenuView::new(phi))
-    });
+use ::phi::{Phi, View};
+use ::phi::window::WindowConfig;
+
+struct Options {
+    headless: Option<u32>,
+    screenshot: Option<String>,
+    record: Option<String>,
+    replay: Option<String>,
 }
 
+impl Options {
+    // `--headless <frames>` runs the game without a window, e.g. on CI,
+    // `--screenshot <file.bmp>` saves its last frame.
+    // `--record <file>` and `--replay <file>` save and play back the input.
+    fn parse() -> Result<Options, String> {
+        let mut options = Options {
+            headless: None,
+            screenshot: None,
+            record: None,
+            replay: None,
+        };
+
+        let mut args = ::std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(format!("missing value for {}", arg))?;
+
+            match &arg[..] {
+                "--headless" => options.headless = Some(value.parse()
+                    .map_err(|_| format!("invalid frame count {}", value))?),
+                "--screenshot" => options.screenshot = Some(value),
+                "--record" => options.record = Some(value),
+                "--replay" => options.replay = Some(value),
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(err) => {
+            println!("{}", err);
+            ::std::process::exit(1);
+        },
+    };
+
+    let init = |phi: &mut Phi| -> Box<View> {
+        let recording = match options.record {
+            Some(ref path) => phi.events.start_recording(path),
+            None => Ok(()),
+        };
+
+        let replaying = match options.replay {
+            Some(ref path) => phi.events.start_replay(path),
+            None => Ok(()),
+        };
+
+        if let Err(err) = recording.and(replaying) {
+            println!("{}", err);
+            ::std::process::exit(1);
+        }
+
+        match ::views::main_menu::MainMenuView::new(phi) {
+            Ok(view) => Box::new(view),
+            Err(err) => {
+                println!("{}", err);
+                ::std::process::exit(1);
+            },
+        }
+    };
+
+    if let Some(frames) = options.headless {
+        let mut headless = ::phi::headless::Headless::new(800, 600, 60, init);
+        println!("Ran {} frames", headless.run(frames));
+
+        if let Some(ref path) = options.screenshot {
+            if let Err(err) = headless.save_frame(path) {
+                println!("{}", err);
+                ::std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    ::phi::spawn("ArcadeRS Shooter", 60, WindowConfig::new(800, 600), init);
+}
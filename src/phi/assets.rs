@@ -0,0 +1,260 @@
+use ::phi::audio::{Audio, Sound};
+use ::phi::gfx::{BitmapFont, Sprite};
+use ::sdl2::pixels::Color;
+use ::sdl2::render::Renderer;
+use ::sdl2_image::LoadTexture;
+use ::sdl2_ttf::Font;
+use ::std::collections::HashMap;
+use ::std::path::{Path, PathBuf};
+use ::std::rc::Rc;
+use ::std::time::SystemTime;
+
+// seconds between two checks for changed files
+const WATCH_INTERVAL: f64 = 0.5;
+// how many rendered strings are kept at least, see `text`
+const TEXT_CACHE_SIZE: usize = 256;
+
+// text, font name, size and color
+type TextKey = (String, String, i32, Color);
+
+// Loads every texture, font and sound once and hands out cheap clones of it
+// afterwards. Assets are named by their path inside the `assets` directory.
+pub struct Assets {
+    root: PathBuf,
+
+    textures: HashMap<String, Sprite>,
+    fonts: HashMap<(String, i32), Rc<Font>>,
+    bitmap_fonts: HashMap<String, Rc<BitmapFont>>,
+    sounds: HashMap<String, Sound>,
+    text: HashMap<TextKey, Sprite>,
+    old_text: HashMap<TextKey, Sprite>,
+
+    // when the loaded files were last modified, see `watch`
+    watching: bool,
+    modified: HashMap<PathBuf, SystemTime>,
+    since_check: f64,
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        Assets::with_root(find_root())
+    }
+
+    pub fn with_root<P: Into<PathBuf>>(root: P) -> Assets {
+        Assets {
+            root: root.into(),
+
+            textures: HashMap::new(),
+            fonts: HashMap::new(),
+            bitmap_fonts: HashMap::new(),
+            sounds: HashMap::new(),
+            text: HashMap::new(),
+            old_text: HashMap::new(),
+
+            watching: false,
+            modified: HashMap::new(),
+            since_check: 0.,
+        }
+    }
+
+    // While watching, `update` reloads textures and fonts whose files have
+    // changed, for editing assets without restarting the game.
+    pub fn watch(&mut self, watching: bool) {
+        self.watching = watching;
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Where the asset called `name` is on disk, or an error naming that path
+    // if there is nothing there.
+    pub fn path(&self, name: &str) -> Result<PathBuf, String> {
+        let path = self.root.join(name);
+
+        if path.is_file() {
+            Ok(path)
+        } else {
+            Err(format!("missing asset {}", path.display()))
+        }
+    }
+
+    pub fn texture(&mut self, renderer: &Renderer, name: &str) -> Result<Sprite, String> {
+        if let Some(sprite) = self.textures.get(name) {
+            return Ok(sprite.clone());
+        }
+
+        let path = self.path(name)?;
+        let sprite = renderer.load_texture(&path).map(Sprite::new)
+            .map_err(|err| format!("could not load texture {}: {}", path.display(), err))?;
+
+        self.remember_modified(path);
+        self.textures.insert(name.to_string(), sprite.clone());
+        Ok(sprite)
+    }
+
+    pub fn font(&mut self, name: &str, size: i32) -> Result<Rc<Font>, String> {
+        let key = (name.to_string(), size);
+        if let Some(font) = self.fonts.get(&key) {
+            return Ok(font.clone());
+        }
+
+        let path = self.path(name)?;
+        let font = Font::from_file(&path, size).map(Rc::new)
+            .map_err(|err| format!("could not load font {}: {}", path.display(), err))?;
+
+        self.remember_modified(path);
+        self.fonts.insert(key, font.clone());
+        Ok(font)
+    }
+
+    // A BMFont descriptor, its images are named relative to it.
+    pub fn bitmap_font(&mut self, renderer: &Renderer, name: &str) -> Result<Rc<BitmapFont>, String> {
+        if let Some(font) = self.bitmap_fonts.get(name) {
+            return Ok(font.clone());
+        }
+
+        let path = self.path(name)?;
+        let font = Rc::new(BitmapFont::load(&path, |page| {
+            let page_name = Path::new(name).with_file_name(page);
+            let page_name = page_name.to_str().ok_or(format!("bad page name {}", page))?;
+            self.texture(renderer, page_name)
+        })?);
+
+        self.bitmap_fonts.insert(name.to_string(), font.clone());
+        Ok(font)
+    }
+
+    pub fn sound(&mut self, audio: &mut Audio, name: &str) -> Result<Sound, String> {
+        if let Some(sound) = self.sounds.get(name) {
+            return Ok(sound.clone());
+        }
+
+        let sound = audio.load(&self.path(name)?)?;
+        self.sounds.insert(name.to_string(), sound.clone());
+        Ok(sound)
+    }
+
+    // Text rendered with the font at `font_name`. The cache has two
+    // generations: once the current one is full, it replaces the old one, so
+    // strings that weren't drawn since are dropped, e.g. outdated scores.
+    pub fn text(&mut self, renderer: &Renderer, text: &str, font_name: &str, size: i32, color: Color) -> Result<Sprite, String> {
+        let key = (text.to_string(), font_name.to_string(), size, color);
+        if let Some(sprite) = self.text.get(&key) {
+            return Ok(sprite.clone());
+        }
+
+        let sprite = match self.old_text.remove(&key) {
+            Some(sprite) => sprite,
+            None => {
+                let font = self.font(font_name, size)?;
+                let surface = font.render(text, ::sdl2_ttf::blended(color))
+                    .map_err(|err| format!("could not render {:?} with {} at size {}: {:?}", text, font_name, size, err))?;
+
+                renderer.create_texture_from_surface(&surface).map(Sprite::new)
+                    .map_err(|err| format!("could not create a texture for {:?}: {:?}", text, err))?
+            },
+        };
+
+        if self.text.len() >= TEXT_CACHE_SIZE {
+            self.old_text = ::std::mem::replace(&mut self.text, HashMap::new());
+        }
+
+        self.text.insert(key, sprite.clone());
+        Ok(sprite)
+    }
+
+    // Checks for changed files every `WATCH_INTERVAL` seconds while watching.
+    // Sprites see a reloaded texture right away. Reloaded fonts only apply to
+    // text rendered afterwards, e.g. not to labels made once and kept.
+    pub fn update(&mut self, renderer: &Renderer, elapsed: f64) {
+        if !self.watching {
+            return;
+        }
+
+        self.since_check += elapsed;
+        if self.since_check < WATCH_INTERVAL {
+            return;
+        }
+        self.since_check = 0.;
+
+        let changed: Vec<PathBuf> = self.modified.iter()
+            .filter(|&(path, &time)| modified(path).map(|now| now != time).unwrap_or(false))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in changed {
+            // also retried on the next check if loading fails, e.g. because
+            // the file is still being written
+            if self.reload(renderer, &path) {
+                println!("Reloaded {}", path.display());
+                self.remember_modified(path);
+            }
+        }
+    }
+
+    fn reload(&mut self, renderer: &Renderer, path: &Path) -> bool {
+        let mut reloaded = true;
+        let mut reloaded_font = false;
+
+        for (name, sprite) in self.textures.iter() {
+            if self.root.join(name) == path {
+                match renderer.load_texture(path) {
+                    Ok(texture) => sprite.replace_texture(texture),
+                    Err(err) => {
+                        println!("could not reload texture {}: {}", path.display(), err);
+                        reloaded = false;
+                    },
+                }
+            }
+        }
+
+        for (&(ref name, size), font) in self.fonts.iter_mut() {
+            if self.root.join(name) == path {
+                match Font::from_file(path, size) {
+                    Ok(new_font) => {
+                        *font = Rc::new(new_font);
+                        reloaded_font = true;
+                    },
+                    Err(err) => {
+                        println!("could not reload font {}: {}", path.display(), err);
+                        reloaded = false;
+                    },
+                }
+            }
+        }
+
+        if reloaded_font {
+            self.text.clear();
+            self.old_text.clear();
+        }
+
+        reloaded
+    }
+
+    fn remember_modified(&mut self, path: PathBuf) {
+        if let Some(time) = modified(&path) {
+            self.modified.insert(path, time);
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    ::std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// The `assets` directory next to the executable, or in one of its parents
+// when running from cargo's `target` directory. Falls back to the working
+// directory.
+fn find_root() -> PathBuf {
+    if let Ok(exe) = ::std::env::current_exe() {
+        for dir in exe.ancestors().skip(1) {
+            let root = dir.join("assets");
+            if root.is_dir() {
+                return root;
+            }
+        }
+    }
+
+    PathBuf::from("assets")
+}
@@ -0,0 +1,147 @@
+use ::phi::assets::Assets;
+use ::phi::data::Rectangle;
+use ::phi::gfx::{AnimatedSprite, PlayMode, Sprite};
+use ::rustc_serialize::json::{Json, Object};
+use ::sdl2::render::Renderer;
+use ::std::collections::HashMap;
+use ::std::fs::File;
+use ::std::io::Read;
+use ::std::path::Path;
+
+// Named regions of a single image, loaded from a JSON descriptor like:
+//
+//     {
+//         "image": "spaceship.png",
+//         "regions": {
+//             "mid_norm": { "x": 43, "y": 39, "w": 43, "h": 39, "pivot": [21, 19] }
+//         },
+//         "animations": {
+//             "idle": { "frames": ["mid_norm", "mid_fast"], "duration": 0.1, "mode": "loop" }
+//         }
+//     }
+//
+// The image is named relative to the descriptor. Pivots default to the
+// region's center, `duration` may also be a list with one entry per frame, and
+// `mode` is one of "loop", "once" or "ping-pong".
+pub struct Atlas {
+    sprites: HashMap<String, Sprite>,
+    pivots: HashMap<String, (f64, f64)>,
+    animations: HashMap<String, AnimatedSprite>,
+}
+
+impl Atlas {
+    pub fn load(assets: &mut Assets, renderer: &Renderer, name: &str) -> Result<Atlas, String> {
+        let path = assets.path(name)?;
+        let mut text = String::new();
+        File::open(&path).and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("could not read atlas {}: {}", path.display(), err))?;
+
+        let json = Json::from_str(&text)
+            .map_err(|err| format!("could not parse atlas {}: {}", path.display(), err))?;
+        let invalid = |what: &str| format!("invalid atlas {}: {}", path.display(), what);
+
+        let image = json.find("image").and_then(Json::as_string)
+            .ok_or_else(|| invalid("missing image"))?;
+        let image_name = Path::new(name).with_file_name(image);
+        let image_name = image_name.to_str().ok_or_else(|| invalid("bad image name"))?;
+        let sheet = assets.texture(renderer, image_name)?;
+
+        let mut atlas = Atlas {
+            sprites: HashMap::new(),
+            pivots: HashMap::new(),
+            animations: HashMap::new(),
+        };
+
+        for (name, region) in object(&json, "regions").ok_or_else(|| invalid("missing regions"))? {
+            let number = |key: &str| region.find(key).and_then(Json::as_f64)
+                .ok_or_else(|| invalid(&format!("region {} needs a number {}", name, key)));
+
+            let rect = Rectangle {
+                x: number("x")?,
+                y: number("y")?,
+                w: number("w")?,
+                h: number("h")?,
+            };
+
+            let sprite = sheet.region(rect)
+                .ok_or_else(|| invalid(&format!("region {} is outside of {}", name, image)))?;
+
+            let pivot = match region.find("pivot").and_then(Json::as_array) {
+                Some(pivot) => match (pivot.get(0).and_then(Json::as_f64), pivot.get(1).and_then(Json::as_f64)) {
+                    (Some(x), Some(y)) => (x, y),
+                    _ => return Err(invalid(&format!("pivot of region {} must be [x, y]", name))),
+                },
+                None => (rect.w / 2., rect.h / 2.),
+            };
+
+            atlas.sprites.insert(name.clone(), sprite);
+            atlas.pivots.insert(name.clone(), pivot);
+        }
+
+        if let Some(animations) = object(&json, "animations") {
+            for (name, animation) in animations {
+                let animation = atlas.parse_animation(animation)
+                    .map_err(|err| invalid(&format!("animation {}: {}", name, err)))?;
+                atlas.animations.insert(name.clone(), animation);
+            }
+        }
+
+        Ok(atlas)
+    }
+
+    fn parse_animation(&self, json: &Json) -> Result<AnimatedSprite, String> {
+        let names = json.find("frames").and_then(Json::as_array)
+            .ok_or("missing frames".to_string())?;
+
+        let mut frames = Vec::with_capacity(names.len());
+        for name in names {
+            let name = name.as_string().ok_or("frames must be region names".to_string())?;
+            frames.push(self.sprite(name).ok_or(format!("unknown region {}", name))?);
+        }
+
+        if frames.is_empty() {
+            return Err("no frames".to_string());
+        }
+
+        let durations = match json.find("duration") {
+            Some(&Json::Array(ref durations)) => durations.iter().map(Json::as_f64).collect(),
+            Some(duration) => duration.as_f64().map(|duration| vec![duration; frames.len()]),
+            None => None,
+        }.ok_or("duration must be a number or a list of numbers".to_string())?;
+
+        if durations.len() != frames.len() || durations.iter().any(|&duration| duration <= 0.) {
+            return Err("needs a positive duration per frame".to_string());
+        }
+
+        let mode = match json.find("mode").map(|mode| mode.as_string()) {
+            None | Some(Some("loop")) => PlayMode::Loop,
+            Some(Some("once")) => PlayMode::Once,
+            Some(Some("ping-pong")) => PlayMode::PingPong,
+            _ => return Err("mode must be loop, once or ping-pong".to_string()),
+        };
+
+        let mut animation = AnimatedSprite::with_durations(frames, durations);
+        animation.set_mode(mode);
+        Ok(animation)
+    }
+
+    // All sprites share the atlas' texture.
+    pub fn sprite(&self, name: &str) -> Option<Sprite> {
+        self.sprites.get(name).cloned()
+    }
+
+    // The point of a region, relative to its top-left corner, that should be
+    // placed at an object's position, which keeps frames of different sizes
+    // aligned.
+    pub fn pivot(&self, name: &str) -> Option<(f64, f64)> {
+        self.pivots.get(name).cloned()
+    }
+
+    pub fn animation(&self, name: &str) -> Option<AnimatedSprite> {
+        self.animations.get(name).cloned()
+    }
+}
+
+fn object<'a>(json: &'a Json, key: &str) -> Option<&'a Object> {
+    json.find(key).and_then(Json::as_object)
+}
@@ -0,0 +1,748 @@
+use ::phi::settings::Settings;
+use ::sdl2::AudioSubsystem;
+use ::sdl2::audio::{AudioCallback, AudioDevice, AudioFormat, AudioSpecDesired, AudioSpecWAV};
+use ::std::fs::File;
+use ::std::io::{BufReader, Read, Seek, SeekFrom};
+use ::std::path::{Path, PathBuf};
+use ::std::sync::Arc;
+use ::std::sync::mpsc::{Receiver, TryRecvError, sync_channel};
+
+const SOUND_CHANNELS: usize = 8;
+// source frames decoded at once when streaming music
+const STREAM_BLOCK_FRAMES: usize = 4096;
+// blocks decoded ahead of the one playing
+const STREAM_BLOCKS: usize = 4;
+
+// Decoded samples, cheap to clone. Sounds loaded while silent hold none.
+#[derive(Clone)]
+pub struct Sound {
+    samples: Arc<Vec<f32>>,
+}
+
+// Interleaved samples in the device's format, played from memory for sounds
+// and decoded on another thread for music, see `stream_wav`.
+enum Source {
+    Samples {
+        samples: Arc<Vec<f32>>,
+        pos: usize,
+        looping: bool,
+    },
+    Stream {
+        blocks: Receiver<Vec<f32>>,
+        block: Vec<f32>,
+        pos: usize,
+        // played while the decoder is late
+        silence: Vec<f32>,
+        ended: bool,
+    },
+}
+
+impl Source {
+    // The samples of the next frame, None once there are no more.
+    fn next_frame(&mut self, channels: usize) -> Option<&[f32]> {
+        match *self {
+            Source::Samples { ref samples, ref mut pos, looping } => {
+                if *pos >= samples.len() {
+                    if looping && !samples.is_empty() {
+                        *pos = 0;
+                    } else {
+                        return None;
+                    }
+                }
+
+                *pos += channels;
+                Some(&samples[*pos - channels..*pos])
+            },
+            Source::Stream { ref blocks, ref mut block, ref mut pos, ref silence, ref mut ended } => {
+                if *pos >= block.len() {
+                    match blocks.try_recv() {
+                        Ok(next) => {
+                            *block = next;
+                            *pos = 0;
+                        },
+                        Err(TryRecvError::Empty) => return Some(silence),
+                        Err(TryRecvError::Disconnected) => {
+                            *ended = true;
+                            return None;
+                        },
+                    }
+                }
+
+                *pos += channels;
+                Some(&block[*pos - channels..*pos])
+            },
+        }
+    }
+
+    fn finished(&self) -> bool {
+        match *self {
+            Source::Samples { ref samples, pos, looping } => !looping && pos >= samples.len(),
+            Source::Stream { ended, .. } => ended,
+        }
+    }
+}
+
+struct Voice {
+    source: Source,
+
+    gain: f32,
+    target_gain: f32,
+    // gain change per frame while fading
+    gain_step: f32,
+}
+
+impl Voice {
+    fn new(source: Source) -> Voice {
+        Voice {
+            source: source,
+
+            gain: 1.,
+            target_gain: 1.,
+            gain_step: 0.,
+        }
+    }
+
+    fn fade_to(&mut self, target_gain: f32, fade_frames: f32) {
+        self.target_gain = target_gain;
+        if fade_frames < 1. {
+            self.gain = target_gain;
+            self.gain_step = 0.;
+        } else {
+            self.gain_step = (target_gain - self.gain).abs() / fade_frames;
+        }
+    }
+
+    fn sound(sound: &Sound) -> Voice {
+        Voice::new(Source::Samples {
+            samples: sound.samples.clone(),
+            pos: 0,
+            looping: false,
+        })
+    }
+
+    fn finished(&self) -> bool {
+        self.source.finished() || (self.gain == 0. && self.target_gain == 0.)
+    }
+
+    // Adds the voice to `out`, frame by frame.
+    fn mix(&mut self, out: &mut [f32], channels: usize, volume: f32) {
+        for frame in out.chunks_mut(channels) {
+            let samples = match self.source.next_frame(channels) {
+                Some(samples) => samples,
+                None => return,
+            };
+
+            if self.gain < self.target_gain {
+                self.gain = (self.gain + self.gain_step).min(self.target_gain);
+            } else if self.gain > self.target_gain {
+                self.gain = (self.gain - self.gain_step).max(self.target_gain);
+            }
+
+            for (sample, &value) in frame.iter_mut().zip(samples) {
+                *sample += value * self.gain * volume;
+            }
+        }
+    }
+}
+
+// Runs on SDL's audio thread, the `Audio` front end changes it through
+// `AudioDevice::lock`.
+pub struct Mixer {
+    channels: usize,
+    freq: i32,
+
+    sounds: Vec<Option<Voice>>,
+    sound_volumes: Vec<f32>,
+    // the current track, plus older ones still fading out
+    music: Vec<Voice>,
+
+    master_volume: f32,
+    sound_volume: f32,
+    music_volume: f32,
+}
+
+impl AudioCallback for Mixer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = 0.;
+        }
+
+        for (voice, &volume) in self.sounds.iter_mut().zip(self.sound_volumes.iter()) {
+            if let Some(ref mut playing) = *voice {
+                playing.mix(out, self.channels, volume * self.sound_volume);
+            }
+
+            if voice.as_ref().map(|playing| playing.finished()).unwrap_or(false) {
+                *voice = None;
+            }
+        }
+
+        for voice in self.music.iter_mut() {
+            voice.mix(out, self.channels, self.music_volume);
+        }
+        self.music.retain(|voice| !voice.finished());
+
+        for sample in out.iter_mut() {
+            *sample = (*sample * self.master_volume).max(-1.).min(1.);
+        }
+    }
+}
+
+// Plays sound effects on a fixed number of channels and loops background
+// music streamed from disk, fading between tracks. Without a device (see
+// `silent`), every call is a no-op, e.g. for headless runs.
+pub struct Audio {
+    device: Option<AudioDevice<Mixer>>,
+    music: Option<PathBuf>,
+    // channels only played through `play_sound_on`
+    reserved: usize,
+}
+
+impl Audio {
+    pub fn open(subsystem: &AudioSubsystem) -> Result<Audio, String> {
+        let desired = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(2),
+            samples: Some(1024),
+        };
+
+        let device = subsystem.open_playback(None, desired, |spec| Mixer {
+            channels: spec.channels as usize,
+            freq: spec.freq,
+
+            sounds: (0..SOUND_CHANNELS).map(|_| None).collect(),
+            sound_volumes: vec![1.; SOUND_CHANNELS],
+            music: Vec::new(),
+
+            master_volume: 1.,
+            sound_volume: 1.,
+            music_volume: 0.6,
+        }).map_err(|err| format!("could not open audio device: {:?}", err))?;
+
+        device.resume();
+
+        Ok(Audio {
+            device: Some(device),
+            music: None,
+            reserved: 0,
+        })
+    }
+
+    pub fn silent() -> Audio {
+        Audio {
+            device: None,
+            music: None,
+            reserved: 0,
+        }
+    }
+
+    // Decodes the WAV file at `path` for the device's format, the asset
+    // manager keeps the result around.
+    pub fn load(&mut self, path: &Path) -> Result<Sound, String> {
+        let samples = match self.device {
+            Some(ref mut device) => {
+                let (freq, channels) = {
+                    let mixer = device.lock();
+                    (mixer.freq, mixer.channels)
+                };
+                decode_wav(path, freq, channels)?
+            },
+            None => Vec::new(),
+        };
+
+        Ok(Sound {
+            samples: Arc::new(samples),
+        })
+    }
+
+    // Keeps the first `count` channels for `play_sound_on`, e.g. for a sound
+    // repeated so often it should cut itself off.
+    pub fn reserve_channels(&mut self, count: usize) {
+        assert!(count < SOUND_CHANNELS);
+        self.reserved = count;
+    }
+
+    // Plays the sound on the first free channel that isn't reserved, returns
+    // which one or None when all are busy.
+    pub fn play_sound(&mut self, sound: &Sound) -> Option<usize> {
+        let reserved = self.reserved;
+        let device = self.device.as_mut()?;
+        let mut mixer = device.lock();
+
+        let channel = reserved + mixer.sounds[reserved..].iter().position(|voice| voice.is_none())?;
+        mixer.sounds[channel] = Some(Voice::sound(sound));
+        Some(channel)
+    }
+
+    // Plays the sound on `channel`, cutting off whatever it was playing.
+    pub fn play_sound_on(&mut self, channel: usize, sound: &Sound) {
+        assert!(channel < SOUND_CHANNELS);
+
+        if let Some(ref mut device) = self.device {
+            device.lock().sounds[channel] = Some(Voice::sound(sound));
+        }
+    }
+
+    pub fn stop_channel(&mut self, channel: usize) {
+        assert!(channel < SOUND_CHANNELS);
+
+        if let Some(ref mut device) = self.device {
+            device.lock().sounds[channel] = None;
+        }
+    }
+
+    // Starts looping the WAV file at `path`, cross-fading from the current
+    // track over `fade` seconds. Does nothing if it's already playing.
+    pub fn play_music(&mut self, path: &Path, fade: f64) -> Result<(), String> {
+        if self.music.as_ref().map(|music| music == path).unwrap_or(false) {
+            return Ok(());
+        }
+
+        if let Some(ref mut device) = self.device {
+            let (freq, channels) = {
+                let mixer = device.lock();
+                (mixer.freq, mixer.channels)
+            };
+            // opened before locking again, the file is read on another thread
+            let blocks = stream_wav(path, freq, channels)?;
+
+            let mut mixer = device.lock();
+            let fade_frames = (fade * freq as f64) as f32;
+
+            for voice in mixer.music.iter_mut() {
+                voice.fade_to(0., fade_frames);
+            }
+
+            let mut voice = Voice::new(Source::Stream {
+                blocks: blocks,
+                block: Vec::new(),
+                pos: 0,
+                silence: vec![0.; channels],
+                ended: false,
+            });
+            if fade_frames >= 1. {
+                voice.gain = 0.;
+            }
+            voice.fade_to(1., fade_frames);
+            mixer.music.push(voice);
+        }
+
+        self.music = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    // All volumes are factors from 0 to 1.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        if let Some(ref mut device) = self.device {
+            device.lock().master_volume = volume.max(0.).min(1.);
+        }
+    }
+
+    pub fn set_sound_volume(&mut self, volume: f32) {
+        if let Some(ref mut device) = self.device {
+            device.lock().sound_volume = volume.max(0.).min(1.);
+        }
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        if let Some(ref mut device) = self.device {
+            device.lock().music_volume = volume.max(0.).min(1.);
+        }
+    }
+
+    pub fn set_channel_volume(&mut self, channel: usize, volume: f32) {
+        assert!(channel < SOUND_CHANNELS);
+
+        if let Some(ref mut device) = self.device {
+            device.lock().sound_volumes[channel] = volume.max(0.).min(1.);
+        }
+    }
+
+    // Sets the volumes found in the `audio` settings section.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        let volumes: [(&str, fn(&mut Audio, f32)); 3] = [
+            ("master_volume", Audio::set_master_volume),
+            ("sound_volume", Audio::set_sound_volume),
+            ("music_volume", Audio::set_music_volume),
+        ];
+
+        for &(key, set) in &volumes {
+            if let Some(value) = settings.get("audio", key) {
+                match value.trim().parse() {
+                    Ok(volume) => set(self, volume),
+                    Err(_) => println!("Invalid audio setting {} = {}", key, value),
+                }
+            }
+        }
+    }
+}
+
+// Loads a WAV file as interleaved f32 samples with the given rate and number
+// of channels.
+fn decode_wav(path: &Path, freq: i32, channels: usize) -> Result<Vec<f32>, String> {
+    let wav = AudioSpecWAV::load_wav(path)
+        .map_err(|err| format!("could not load sound {}: {:?}", path.display(), err))?;
+
+    if wav.channels == 0 || wav.freq <= 0 {
+        return Err(format!("{} has no samples to play", path.display()));
+    }
+
+    let samples = to_f32(wav.format, wav.buffer())
+        .ok_or(format!("unsupported sample format {:?} in {}", wav.format, path.display()))?;
+
+    let mut out = Vec::new();
+    let mut resampler = Resampler::new(wav.freq, wav.channels as usize, freq, channels);
+    resampler.push(&samples, &mut out);
+    resampler.finish(&mut out);
+    Ok(out)
+}
+
+// Samples as f32 from -1 to 1, None for formats that aren't supported. An
+// incomplete sample at the end is left out.
+fn to_f32(format: AudioFormat, bytes: &[u8]) -> Option<Vec<f32>> {
+    Some(match format {
+        AudioFormat::U8 => bytes.iter().map(|&b| (b as f32 - 128.) / 128.).collect(),
+        AudioFormat::S8 => bytes.iter().map(|&b| b as i8 as f32 / 128.).collect(),
+        AudioFormat::S16LSB => bytes.chunks_exact(2)
+            .map(|b| (b[0] as u16 | (b[1] as u16) << 8) as i16 as f32 / 32768.).collect(),
+        AudioFormat::S16MSB => bytes.chunks_exact(2)
+            .map(|b| (b[1] as u16 | (b[0] as u16) << 8) as i16 as f32 / 32768.).collect(),
+        AudioFormat::F32LSB => bytes.chunks_exact(4)
+            .map(|b| f32::from_bits(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24))
+            .collect(),
+        _ => return None,
+    })
+}
+
+// Resamples interleaved frames linearly to another rate and number of
+// channels. The input may come in pieces, as long as `finish` is called
+// after the last one.
+struct Resampler {
+    src_channels: usize,
+    channels: usize,
+    // source frames per output frame
+    step: f64,
+
+    // source frames the next output frames are interpolated from
+    src: Vec<f32>,
+    // where in `src` the next output frame is, in frames
+    pos: f64,
+}
+
+impl Resampler {
+    fn new(src_freq: i32, src_channels: usize, freq: i32, channels: usize) -> Resampler {
+        assert!(src_freq > 0 && src_channels > 0 && freq > 0 && channels > 0);
+
+        Resampler {
+            src_channels: src_channels,
+            channels: channels,
+            step: src_freq as f64 / freq as f64,
+
+            src: Vec::new(),
+            pos: 0.,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        self.src.extend_from_slice(samples);
+        self.resample(out, false);
+    }
+
+    fn finish(&mut self, out: &mut Vec<f32>) {
+        self.resample(out, true);
+    }
+
+    // Until the last piece, frames are only interpolated once the source
+    // frame after them has arrived.
+    fn resample(&mut self, out: &mut Vec<f32>, last: bool) {
+        let src_frames = self.src.len() / self.src_channels;
+
+        loop {
+            let i_src = self.pos as usize;
+            if i_src >= src_frames || (!last && i_src + 1 >= src_frames) {
+                break;
+            }
+
+            let i_next = (i_src + 1).min(src_frames - 1);
+            let t = (self.pos - i_src as f64) as f32;
+
+            for channel in 0..self.channels {
+                // extra channels repeat the source's last one, e.g. mono to stereo
+                let src_channel = channel.min(self.src_channels - 1);
+                let a = self.src[i_src * self.src_channels + src_channel];
+                let b = self.src[i_next * self.src_channels + src_channel];
+                out.push(a + (b - a) * t);
+            }
+
+            self.pos += self.step;
+        }
+
+        let used = (self.pos as usize).min(src_frames);
+        self.src.drain(..used * self.src_channels);
+        self.pos -= used as f64;
+    }
+}
+
+// Where the samples are in a WAV file and how to read them.
+#[derive(Debug, PartialEq)]
+struct WavInfo {
+    format: AudioFormat,
+    channels: usize,
+    freq: i32,
+    data_start: u64,
+    // in bytes, whole frames only
+    data_len: u64,
+}
+
+impl WavInfo {
+    fn frame_size(&self) -> usize {
+        let sample_size = match self.format {
+            AudioFormat::U8 | AudioFormat::S8 => 1,
+            AudioFormat::F32LSB => 4,
+            _ => 2,
+        };
+
+        sample_size * self.channels
+    }
+}
+
+// Reads the header of a PCM or float WAV file, leaving `reader` at the start
+// of the samples.
+fn read_wav_info<R: Read + Seek>(reader: &mut R) -> Result<WavInfo, String> {
+    fn read_bytes<R: Read>(reader: &mut R, count: usize) -> Result<Vec<u8>, String> {
+        let mut bytes = vec![0; count];
+        reader.read_exact(&mut bytes).map_err(|err| format!("truncated WAV header: {}", err))?;
+        Ok(bytes)
+    }
+
+    let u16_at = |bytes: &[u8], i: usize| bytes[i] as u16 | (bytes[i + 1] as u16) << 8;
+    let u32_at = |bytes: &[u8], i: usize| u16_at(bytes, i) as u32 | (u16_at(bytes, i + 2) as u32) << 16;
+
+    let riff = read_bytes(reader, 12)?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err("not a WAV file".to_string());
+    }
+
+    let mut format = None;
+
+    loop {
+        let chunk = read_bytes(reader, 8)?;
+        let size = u32_at(&chunk, 4) as u64;
+
+        match &chunk[..4] {
+            b"fmt " if size >= 16 => {
+                let fmt = read_bytes(reader, size as usize)?;
+                let mut tag = u16_at(&fmt, 0);
+                // WAVE_FORMAT_EXTENSIBLE, the actual tag is in the sub format
+                if tag == 0xFFFE && size >= 26 {
+                    tag = u16_at(&fmt, 24);
+                }
+
+                let sample_format = match (tag, u16_at(&fmt, 14)) {
+                    (1, 8) => AudioFormat::U8,
+                    (1, 16) => AudioFormat::S16LSB,
+                    (3, 32) => AudioFormat::F32LSB,
+                    (tag, bits) => return Err(format!("unsupported WAV format {} with {} bits", tag, bits)),
+                };
+
+                format = Some((sample_format, u16_at(&fmt, 2) as usize, u32_at(&fmt, 4) as i32));
+            },
+            b"data" => {
+                let (format, channels, freq) = format.ok_or("WAV samples before their format".to_string())?;
+
+                if channels == 0 || freq <= 0 {
+                    return Err("no samples to play".to_string());
+                }
+
+                let data_start = reader.seek(SeekFrom::Current(0))
+                    .map_err(|err| format!("could not read WAV file: {}", err))?;
+
+                let mut info = WavInfo {
+                    format: format,
+                    channels: channels,
+                    freq: freq,
+                    data_start: data_start,
+                    data_len: 0,
+                };
+                info.data_len = size - size % info.frame_size() as u64;
+
+                if info.data_len == 0 {
+                    return Err("no samples to play".to_string());
+                }
+
+                return Ok(info);
+            },
+            _ => {
+                // chunks are padded to an even size
+                reader.seek(SeekFrom::Current((size + size % 2) as i64))
+                    .map_err(|err| format!("could not read WAV file: {}", err))?;
+            },
+        }
+    }
+}
+
+// Decodes the WAV file at `path` on a thread of its own, looping, and sends
+// its samples for the given rate and number of channels in blocks. The
+// thread stops once the receiver is dropped.
+fn stream_wav(path: &Path, freq: i32, channels: usize) -> Result<Receiver<Vec<f32>>, String> {
+    let mut reader = File::open(path).map(BufReader::new)
+        .map_err(|err| format!("could not open music {}: {}", path.display(), err))?;
+    let wav = read_wav_info(&mut reader)
+        .map_err(|err| format!("could not stream music {}: {}", path.display(), err))?;
+
+    let (sender, receiver) = sync_channel(STREAM_BLOCKS);
+    let path = path.to_path_buf();
+
+    ::std::thread::spawn(move || {
+        let mut resampler = Resampler::new(wav.freq, wav.channels, freq, channels);
+        let mut bytes = vec![0; STREAM_BLOCK_FRAMES * wav.frame_size()];
+        let mut left = wav.data_len;
+
+        loop {
+            if left == 0 {
+                if let Err(err) = reader.seek(SeekFrom::Start(wav.data_start)) {
+                    println!("Stopping music {}: {}", path.display(), err);
+                    return;
+                }
+                left = wav.data_len;
+            }
+
+            let count = (bytes.len() as u64).min(left) as usize;
+            if let Err(err) = reader.read_exact(&mut bytes[..count]) {
+                println!("Stopping music {}: {}", path.display(), err);
+                return;
+            }
+            left -= count as u64;
+
+            // the format was checked along with the header
+            let samples = to_f32(wav.format, &bytes[..count]).unwrap();
+            let mut block = Vec::new();
+            resampler.push(&samples, &mut block);
+
+            if !block.is_empty() && sender.send(block).is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::std::io::Cursor;
+
+    fn wav_bytes(fmt: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        // chunks the reader doesn't know are skipped, padding included
+        bytes.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&[fmt.len() as u8, 0, 0, 0]);
+        bytes.extend_from_slice(fmt);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&[data.len() as u8, 0, 0, 0]);
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    // tag, channels, rate, byte rate, block align and bits per sample
+    fn fmt(tag: u16, channels: u16, freq: u32, bits: u16) -> Vec<u8> {
+        let align = channels * bits / 8;
+        let mut fmt = Vec::new();
+        for &(value, size) in &[(tag as u32, 2), (channels as u32, 2), (freq, 4), (freq * align as u32, 4), (align as u32, 2), (bits as u32, 2)] {
+            for i in 0..size {
+                fmt.push((value >> (i * 8)) as u8);
+            }
+        }
+        fmt
+    }
+
+    fn resample(samples: &[f32], src_freq: i32, src_channels: usize, freq: i32, channels: usize, piece: usize) -> Vec<f32> {
+        let mut out = Vec::new();
+        let mut resampler = Resampler::new(src_freq, src_channels, freq, channels);
+        for samples in samples.chunks(piece) {
+            resampler.push(samples, &mut out);
+        }
+        resampler.finish(&mut out);
+        out
+    }
+
+    #[test]
+    fn incomplete_samples_are_left_out() {
+        assert_eq!(to_f32(AudioFormat::S16LSB, &[0, 0x40, 0, 0x80, 0x7F]), Some(vec![0.5, -1.]));
+        assert_eq!(to_f32(AudioFormat::S16MSB, &[0x40, 0]), Some(vec![0.5]));
+        assert_eq!(to_f32(AudioFormat::F32LSB, &[0, 0, 0x80, 0x3F, 0, 0]), Some(vec![1.]));
+        assert_eq!(to_f32(AudioFormat::U8, &[128, 0]), Some(vec![0., -1.]));
+        assert_eq!(to_f32(AudioFormat::S32LSB, &[0; 4]), None);
+    }
+
+    #[test]
+    fn resampling_keeps_the_same_rate_as_it_is() {
+        let samples = [0., 0.5, -0.5, 1., 0.25];
+        assert_eq!(resample(&samples, 44100, 1, 44100, 1, 2), samples.to_vec());
+    }
+
+    #[test]
+    fn resampling_interpolates_and_spreads_channels() {
+        let samples = [0., 1., 0.];
+        assert_eq!(resample(&samples, 1, 1, 2, 2, 3), vec![0., 0., 0.5, 0.5, 1., 1., 0.5, 0.5, 0., 0., 0., 0.]);
+        assert_eq!(resample(&[0., 1., 2., 3.], 2, 1, 1, 1, 4), vec![0., 2.]);
+    }
+
+    #[test]
+    fn resampling_in_pieces_gives_the_same_samples() {
+        let samples: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.1).sin()).collect();
+        let whole = resample(&samples, 22050, 2, 44100, 2, samples.len());
+
+        for &piece in &[2, 6, 100, 998] {
+            assert_eq!(resample(&samples, 22050, 2, 44100, 2, piece), whole);
+        }
+
+        let whole = resample(&samples, 48000, 1, 44100, 2, samples.len());
+        assert_eq!(resample(&samples, 48000, 1, 44100, 2, 7), whole);
+    }
+
+    #[test]
+    fn wav_header_is_read() {
+        let bytes = wav_bytes(&fmt(1, 2, 22050, 16), &[0; 10]);
+        let mut reader = Cursor::new(bytes);
+        let info = read_wav_info(&mut reader).unwrap();
+
+        assert_eq!(info, WavInfo {
+            format: AudioFormat::S16LSB,
+            channels: 2,
+            freq: 22050,
+            data_start: 56,
+            // two whole frames of four bytes
+            data_len: 8,
+        });
+        assert_eq!(reader.position(), 56);
+    }
+
+    #[test]
+    fn bad_wav_files_are_rejected() {
+        for bytes in &[
+            b"RIFX\0\0\0\0WAVE".to_vec(),
+            wav_bytes(&fmt(1, 0, 22050, 16), &[0; 4]),
+            wav_bytes(&fmt(1, 1, 0, 16), &[0; 4]),
+            wav_bytes(&fmt(1, 1, 22050, 24), &[0; 6]),
+            wav_bytes(&fmt(1, 2, 22050, 16), &[0; 3]),
+            wav_bytes(&fmt(1, 1, 22050, 16), &[])[..40].to_vec(),
+        ] {
+            assert!(read_wav_info(&mut Cursor::new(bytes)).is_err());
+        }
+    }
+
+    #[test]
+    fn music_streams_in_whole_frames() {
+        let blocks = stream_wav(Path::new("assets/menu.wav"), 44100, 2).unwrap();
+
+        for _ in 0..3 {
+            let block = blocks.recv().unwrap();
+            assert!(!block.is_empty() && block.len() % 2 == 0);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use ::phi::Action;
+use ::phi::replay::{escape_chars, unescape};
+use ::phi::settings::Settings;
+use ::sdl2::controller::Button;
+use ::sdl2::keyboard::Keycode;
+use ::std::collections::HashMap;
+
+const SECTION: &'static str = "bindings";
+const CONTROLLER_SECTION: &'static str = "controller";
+
+// Maps every logical action to the physical keys and controller buttons
+// triggering it. A key may be bound to several actions, e.g. Space both fires
+// and confirms menu entries.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: HashMap<Action, Vec<Keycode>>,
+    buttons: HashMap<Action, Vec<Button>>,
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings {
+            keys: Action::all().iter()
+                .map(|&action| (action, action.default_keys()))
+                .collect(),
+            buttons: Action::all().iter()
+                .map(|&action| (action, action.default_buttons()))
+                .collect(),
+        }
+    }
+
+    // Actions missing from the settings keep their default keys.
+    pub fn from_settings(settings: &Settings) -> Bindings {
+        let mut bindings = Bindings::new();
+
+        for &action in Action::all() {
+            if let Some(value) = settings.get(SECTION, action.name()) {
+                let keys = value.split(',')
+                    .map(|name| unescape(name.trim()))
+                    .filter(|name| !name.is_empty())
+                    .filter_map(|name| {
+                        let key = Keycode::from_name(&name);
+                        if key.is_none() {
+                            println!("Unknown key {} bound to {}", name, action.name());
+                        }
+                        key
+                    })
+                    .collect();
+
+                bindings.set(action, keys);
+            }
+
+            if let Some(value) = settings.get(CONTROLLER_SECTION, action.name()) {
+                let buttons = value.split(',')
+                    .map(|name| name.trim())
+                    .filter(|name| !name.is_empty())
+                    .filter_map(|name| {
+                        let button = Button::from_string(name);
+                        if button.is_none() {
+                            println!("Unknown controller button {} bound to {}", name, action.name());
+                        }
+                        button
+                    })
+                    .collect();
+
+                bindings.set_buttons(action, buttons);
+            }
+        }
+
+        bindings
+    }
+
+    pub fn write_settings(&self, settings: &mut Settings) {
+        for &action in Action::all() {
+            // the Comma key is named ","
+            let names: Vec<String> = self.keys(action).iter()
+                .map(|key| escape_chars(&key.name(), ","))
+                .collect();
+            settings.set(SECTION, action.name(), &names.join(", "));
+
+            let names: Vec<String> = self.buttons(action).iter().map(|button| button.string()).collect();
+            settings.set(CONTROLLER_SECTION, action.name(), &names.join(", "));
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[Keycode] {
+        self.keys.get(&action).map(|keys| &keys[..]).unwrap_or(&[])
+    }
+
+    pub fn set(&mut self, action: Action, keys: Vec<Keycode>) {
+        self.keys.insert(action, keys);
+    }
+
+    pub fn actions(&self, key: Keycode) -> Vec<Action> {
+        Action::all().iter()
+            .cloned()
+            .filter(|&action| self.keys(action).contains(&key))
+            .collect()
+    }
+
+    pub fn buttons(&self, action: Action) -> &[Button] {
+        self.buttons.get(&action).map(|buttons| &buttons[..]).unwrap_or(&[])
+    }
+
+    pub fn set_buttons(&mut self, action: Action, buttons: Vec<Button>) {
+        self.buttons.insert(action, buttons);
+    }
+
+    pub fn button_actions(&self, button: Button) -> Vec<Action> {
+        Action::all().iter()
+            .cloned()
+            .filter(|&action| self.buttons(action).contains(&button))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_survive_saving_and_loading() {
+        let mut bindings = Bindings::new();
+        bindings.set(Action::Fire, vec![Keycode::Comma, Keycode::Space, Keycode::LShift, Keycode::Underscore]);
+        bindings.set(Action::Pause, vec![]);
+
+        let mut settings = Settings::new();
+        bindings.write_settings(&mut settings);
+        let loaded = Bindings::from_settings(&Settings::parse(&settings.to_string()).unwrap());
+
+        for &action in Action::all() {
+            assert_eq!(loaded.keys(action), bindings.keys(action), "{}", action.name());
+            assert_eq!(loaded.buttons(action), bindings.buttons(action), "{}", action.name());
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use ::phi::data::Rectangle;
+
+// Which part of the world is seen on screen. World coordinates are pixels at a
+// zoom of 1, and the camera's position is the point shown at the center of
+// the screen.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pos: (f64, f64),
+    prev_pos: (f64, f64),
+    zoom: f64,
+    // the size of the screen area the world is drawn on
+    viewport: (f64, f64),
+    // the world region the camera shows nothing outside of
+    bounds: Option<Rectangle>,
+
+    target: Option<(f64, f64)>,
+    // the part of the distance to the target still left after a second
+    smoothing: f64,
+
+    // seconds of shaking left and for how long it started, see `shake`
+    shake_left: f64,
+    shake_time: f64,
+    shake_strength: f64,
+    shake_offset: (f64, f64),
+}
+
+impl Camera {
+    // Shows the world region of the viewport's size at the origin.
+    pub fn new(viewport_w: f64, viewport_h: f64) -> Camera {
+        let pos = (viewport_w / 2., viewport_h / 2.);
+
+        Camera {
+            pos: pos,
+            prev_pos: pos,
+            zoom: 1.,
+            viewport: (viewport_w, viewport_h),
+            bounds: None,
+
+            target: None,
+            smoothing: 0.05,
+
+            shake_left: 0.,
+            shake_time: 0.,
+            shake_strength: 0.,
+            shake_offset: (0., 0.),
+        }
+    }
+
+    pub fn pos(&self) -> (f64, f64) {
+        self.pos
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f64) {
+        assert!(zoom > 0.);
+        self.zoom = zoom;
+        self.clamp();
+    }
+
+    pub fn set_viewport(&mut self, w: f64, h: f64) {
+        self.viewport = (w, h);
+        self.clamp();
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<Rectangle>) {
+        self.bounds = bounds;
+        self.clamp();
+    }
+
+    // 0 follows the target exactly, values closer to 1 lag further behind.
+    pub fn set_smoothing(&mut self, smoothing: f64) {
+        assert!(smoothing >= 0. && smoothing < 1.);
+        self.smoothing = smoothing;
+    }
+
+    // Moves right to a point, without interpolating from the previous one.
+    pub fn look_at(&mut self, x: f64, y: f64) {
+        self.pos = (x, y);
+        self.clamp();
+        self.prev_pos = self.pos;
+    }
+
+    // Moves towards the point over the next updates, until another target is
+    // set or `unfollow` is called.
+    pub fn follow(&mut self, x: f64, y: f64) {
+        self.target = Some((x, y));
+    }
+
+    pub fn unfollow(&mut self) {
+        self.target = None;
+    }
+
+    // Shakes the view by up to `strength` screen pixels, fading out over
+    // `duration` seconds. A weaker shake doesn't cut a stronger one short.
+    pub fn shake(&mut self, strength: f64, duration: f64) {
+        if self.shake_left > 0. && strength < self.shake_strength * self.shake_left / self.shake_time {
+            return;
+        }
+
+        self.shake_strength = strength;
+        self.shake_left = duration;
+        self.shake_time = duration;
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.prev_pos = self.pos;
+
+        if let Some((x, y)) = self.target {
+            let step = 1. - self.smoothing.powf(dt);
+            self.pos.0 += (x - self.pos.0) * step;
+            self.pos.1 += (y - self.pos.1) * step;
+            self.clamp();
+        }
+
+        self.shake_left = (self.shake_left - dt).max(0.);
+        self.shake_offset =
+            if self.shake_left == 0. { (0., 0.) }
+            else {
+                // irregular but deterministic, so that replays look the same
+                let t = self.shake_time - self.shake_left;
+                let strength = self.shake_strength * self.shake_left / self.shake_time;
+                (strength * (t * 61.).sin() * (t * 17.).cos(), strength * (t * 53.).cos() * (t * 23.).sin())
+            };
+    }
+
+    // The camera as it should be drawn between the previous update and the
+    // last one, `alpha` being as given to `View::draw`.
+    pub fn interpolated(&self, alpha: f64) -> Camera {
+        let mut camera = *self;
+        camera.pos = (
+            self.prev_pos.0 + (self.pos.0 - self.prev_pos.0) * alpha,
+            self.prev_pos.1 + (self.pos.1 - self.prev_pos.1) * alpha,
+        );
+        camera.prev_pos = camera.pos;
+        camera
+    }
+
+    // The world region on screen, not counting the shaking.
+    pub fn view(&self) -> Rectangle {
+        let w = self.viewport.0 / self.zoom;
+        let h = self.viewport.1 / self.zoom;
+
+        Rectangle {
+            x: self.pos.0 - w / 2.,
+            y: self.pos.1 - h / 2.,
+            w: w,
+            h: h,
+        }
+    }
+
+    pub fn to_screen(&self, rect: Rectangle) -> Rectangle {
+        let (x, y) = self.point_to_screen(rect.x, rect.y);
+
+        Rectangle {
+            x: x,
+            y: y,
+            w: rect.w * self.zoom,
+            h: rect.h * self.zoom,
+        }
+    }
+
+    pub fn point_to_screen(&self, x: f64, y: f64) -> (f64, f64) {
+        let view = self.view();
+        ((x - view.x) * self.zoom + self.shake_offset.0, (y - view.y) * self.zoom + self.shake_offset.1)
+    }
+
+    // Where on the world a point of the screen is, e.g. the mouse.
+    pub fn to_world(&self, x: f64, y: f64) -> (f64, f64) {
+        let view = self.view();
+        (view.x + (x - self.shake_offset.0) / self.zoom, view.y + (y - self.shake_offset.1) / self.zoom)
+    }
+
+    // Keeps the view inside the bounds, or centered on them where they're
+    // smaller than the view.
+    fn clamp(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let view = self.view();
+
+        self.pos.0 =
+            if view.w >= bounds.w { bounds.x + bounds.w / 2. }
+            else { self.pos.0.max(bounds.x + view.w / 2.).min(bounds.x + bounds.w - view.w / 2.) };
+
+        self.pos.1 =
+            if view.h >= bounds.h { bounds.y + bounds.h / 2. }
+            else { self.pos.1.max(bounds.y + view.h / 2.).min(bounds.y + bounds.h - view.h / 2.) };
+    }
+}
@@ -1,4 +1,145 @@
 use ::sdl2::rect::Rect as SdlRect;
+use ::std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub fn new(x: f64, y: f64) -> Vec2 {
+        Vec2 { x: x, y: y }
+    }
+
+    // A unit vector `angle` radians clockwise from the x axis, y pointing down.
+    pub fn from_angle(angle: f64) -> Vec2 {
+        Vec2::new(angle.cos(), angle.sin())
+    }
+
+    pub fn length(self) -> f64 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn length_squared(self) -> f64 {
+        self.dot(self)
+    }
+
+    // The same direction at length 1, the zero vector stays as it is.
+    pub fn normalize(self) -> Vec2 {
+        let length = self.length();
+        if length == 0. { self } else { self / length }
+    }
+
+    // Shortened to `max` if it's any longer.
+    pub fn clamp_length(self, max: f64) -> Vec2 {
+        if self.length_squared() > max * max { self.normalize() * max } else { self }
+    }
+
+    pub fn dot(self, other: Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    // The z component of the 3D cross product, positive when `other` is
+    // clockwise from `self` as y points down.
+    pub fn cross(self, other: Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    // Turned a quarter clockwise, as y points down.
+    pub fn perp(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+
+    // `self` at t = 0 and `other` at t = 1.
+    pub fn lerp(self, other: Vec2, t: f64) -> Vec2 {
+        self + (other - self) * t
+    }
+
+    // Turned by `angle` radians, clockwise as y points down.
+    pub fn rotate(self, angle: f64) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+impl From<(f64, f64)> for Vec2 {
+    fn from((x, y): (f64, f64)) -> Vec2 {
+        Vec2::new(x, y)
+    }
+}
+
+impl From<Vec2> for (f64, f64) {
+    fn from(vec: Vec2) -> (f64, f64) {
+        (vec.x, vec.y)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f64> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, factor: f64) -> Vec2 {
+        Vec2::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Mul<Vec2> for f64 {
+    type Output = Vec2;
+
+    fn mul(self, vec: Vec2) -> Vec2 {
+        vec * self
+    }
+}
+
+impl Div<f64> for Vec2 {
+    type Output = Vec2;
+
+    fn div(self, divisor: f64) -> Vec2 {
+        Vec2::new(self.x / divisor, self.y / divisor)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<f64> for Vec2 {
+    fn mul_assign(&mut self, factor: f64) {
+        *self = *self * factor;
+    }
+}
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Rectangle {
@@ -9,6 +150,41 @@ pub struct Rectangle {
 }
 
 impl Rectangle {
+    pub fn from_vecs(pos: Vec2, size: Vec2) -> Rectangle {
+        Rectangle {
+            x: pos.x,
+            y: pos.y,
+            w: size.x,
+            h: size.y,
+        }
+    }
+
+    // A rectangle of `size` with its center at `center`.
+    pub fn centered(center: Vec2, size: Vec2) -> Rectangle {
+        Rectangle::from_vecs(center - size / 2., size)
+    }
+
+    // The top-left corner.
+    pub fn pos(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.w, self.h)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.pos() + self.size() / 2.
+    }
+
+    pub fn with_pos(self, pos: Vec2) -> Rectangle {
+        Rectangle::from_vecs(pos, self.size())
+    }
+
+    pub fn translate(self, offset: Vec2) -> Rectangle {
+        self.with_pos(self.pos() + offset)
+    }
+
     pub fn to_sdl(self) -> Option<SdlRect> {
         assert!(self.w >= 0. && self.h >= 0.);
 
@@ -44,6 +220,11 @@ impl Rectangle {
         ymax >= self.y && ymax <= self.y + self.h
     }
 
+    pub fn contains_point(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.w &&
+        y >= self.y && y < self.y + self.h
+    }
+
     pub fn overlaps(&self, other: Rectangle) -> bool {
         self.x < other.x + other.w &&
         self.x + self.w > other.x &&
@@ -52,3 +233,482 @@ impl Rectangle {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f64,
+}
+
+// Every point within `radius` of the segment from `start` to `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capsule {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub radius: f64,
+}
+
+// A convex polygon, its points going around in either direction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    points: Vec<Vec2>,
+}
+
+impl Polygon {
+    // None unless there are at least 3 points making a convex polygon.
+    pub fn new(points: Vec<Vec2>) -> Option<Polygon> {
+        if points.len() < 3 {
+            return None;
+        }
+
+        let turns: Vec<(f64, f64)> = (0..points.len()).map(|i| {
+            let (a, b, c) = (points[i], points[(i + 1) % points.len()], points[(i + 2) % points.len()]);
+            ((b - a).cross(c - b), (b - a).dot(c - b))
+        }).collect();
+
+        let convex = turns.iter().all(|&(turn, _)| turn >= 0.) || turns.iter().all(|&(turn, _)| turn <= 0.);
+        let flat = turns.iter().all(|&(turn, _)| turn == 0.);
+        // turning the same way at every point still allows going around
+        // more than once, like a pentagram does
+        let winding: f64 = turns.iter().map(|&(cross, dot)| cross.atan2(dot)).sum();
+        let once = (winding.abs() - 2. * ::std::f64::consts::PI).abs() < 1e-6;
+
+        if convex && !flat && once {
+            Some(Polygon { points: points })
+        } else {
+            None
+        }
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    // Turned by `angle` radians around `pivot`.
+    pub fn rotate(&self, angle: f64, pivot: Vec2) -> Polygon {
+        Polygon { points: self.points.iter().map(|&point| pivot + (point - pivot).rotate(angle)).collect() }
+    }
+
+    pub fn translate(&self, offset: Vec2) -> Polygon {
+        Polygon { points: self.points.iter().map(|&point| point + offset).collect() }
+    }
+}
+
+impl From<Rectangle> for Polygon {
+    fn from(rect: Rectangle) -> Polygon {
+        Polygon {
+            points: vec![
+                rect.pos(),
+                Vec2::new(rect.x + rect.w, rect.y),
+                Vec2::new(rect.x + rect.w, rect.y + rect.h),
+                Vec2::new(rect.x, rect.y + rect.h),
+            ],
+        }
+    }
+}
+
+// Any of the shapes, tested against each other. Each one is handled as the
+// points within `radius` of a convex core: a point for circles, a segment for
+// capsules and the polygon itself, with a radius of 0, for polygons.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle(Circle),
+    Capsule(Capsule),
+    Polygon(Polygon),
+}
+
+// how close a swept shape must get to count as touching, see `Shape::sweep`
+const SWEEP_EPSILON: f64 = 1e-3;
+// steps before giving up on shapes that keep getting closer without touching,
+// e.g. when one only grazes the other
+const SWEEP_STEPS: usize = 64;
+
+impl Shape {
+    pub fn translate(&self, offset: Vec2) -> Shape {
+        match *self {
+            Shape::Circle(circle) => Shape::Circle(Circle { center: circle.center + offset, ..circle }),
+            Shape::Capsule(capsule) => Shape::Capsule(Capsule {
+                start: capsule.start + offset,
+                end: capsule.end + offset,
+                ..capsule
+            }),
+            Shape::Polygon(ref polygon) => Shape::Polygon(polygon.translate(offset)),
+        }
+    }
+
+    fn core(&self) -> (Vec<Vec2>, f64) {
+        match *self {
+            Shape::Circle(circle) => (vec![circle.center], circle.radius),
+            Shape::Capsule(capsule) => (vec![capsule.start, capsule.end], capsule.radius),
+            Shape::Polygon(ref polygon) => (polygon.points.clone(), 0.),
+        }
+    }
+
+    pub fn overlaps(&self, other: &Shape) -> bool {
+        self.penetration(other).is_some()
+    }
+
+    // How far `self` has to move for the shapes to only touch, or None if
+    // they don't overlap.
+    pub fn penetration(&self, other: &Shape) -> Option<Vec2> {
+        let (core, radius) = self.core();
+        let (other_core, other_radius) = other.core();
+        let radii = radius + other_radius;
+
+        if !cores_overlap(&core, &other_core) {
+            let (closest, other_closest) = closest_points(&core, &other_core);
+            let distance = (other_closest - closest).length();
+
+            if distance >= radii {
+                return None;
+            }
+
+            // cores only just apart are pushed like overlapping ones
+            if distance > 0. {
+                return Some((closest - other_closest) / distance * (radii - distance));
+            }
+        }
+
+        // the overlapping cores are pushed apart along the edge normal that
+        // takes the shortest move
+        let axes: Vec<Vec2> = edges(&core).into_iter().chain(edges(&other_core))
+            .filter(|&(start, end)| start != end)
+            .map(|(start, end)| (end - start).perp().normalize())
+            .collect();
+
+        let axes = if axes.is_empty() { vec![Vec2::new(1., 0.)] } else { axes };
+
+        axes.into_iter().map(|axis| {
+            let (min, max) = project(&core, axis);
+            let (other_min, other_max) = project(&other_core, axis);
+
+            let forward = other_max - min + radii;
+            let backward = max - other_min + radii;
+
+            if forward < backward { axis * forward } else { axis * -backward }
+        }).fold(None, |shortest: Option<Vec2>, push| match shortest {
+            Some(shortest) if shortest.length_squared() <= push.length_squared() => Some(shortest),
+            _ => Some(push),
+        })
+    }
+
+    // The gap between the shapes, 0 if they touch or overlap.
+    pub fn distance(&self, other: &Shape) -> f64 {
+        let (core, radius) = self.core();
+        let (other_core, other_radius) = other.core();
+
+        if cores_overlap(&core, &other_core) {
+            return 0.;
+        }
+
+        let (closest, other_closest) = closest_points(&core, &other_core);
+        ((other_closest - closest).length() - radius - other_radius).max(0.)
+    }
+
+    // When `self` moving by `motion` first touches `other`, as a fraction of
+    // the motion from 0 to 1, or None if it gets past. Catches hits that
+    // testing only where a fast shape ends up would miss.
+    pub fn sweep(&self, motion: Vec2, other: &Shape) -> Option<f64> {
+        let speed = motion.length();
+        let mut t = 0.;
+
+        // moving by the current distance can't go through `other`, as no
+        // point of the shape moves farther than that
+        for _ in 0..SWEEP_STEPS {
+            let distance = self.translate(motion * t).distance(other);
+
+            if distance < SWEEP_EPSILON {
+                return Some(t);
+            }
+
+            if speed == 0. {
+                return None;
+            }
+
+            t += distance / speed;
+            if t > 1. {
+                return None;
+            }
+        }
+
+        None
+    }
+}
+
+impl From<Circle> for Shape {
+    fn from(circle: Circle) -> Shape {
+        Shape::Circle(circle)
+    }
+}
+
+impl From<Capsule> for Shape {
+    fn from(capsule: Capsule) -> Shape {
+        Shape::Capsule(capsule)
+    }
+}
+
+impl From<Polygon> for Shape {
+    fn from(polygon: Polygon) -> Shape {
+        Shape::Polygon(polygon)
+    }
+}
+
+impl From<Rectangle> for Shape {
+    fn from(rect: Rectangle) -> Shape {
+        Shape::Polygon(Polygon::from(rect))
+    }
+}
+
+// The segments around a core, a single one for points and segments.
+fn edges(points: &[Vec2]) -> Vec<(Vec2, Vec2)> {
+    match points.len() {
+        1 => vec![(points[0], points[0])],
+        2 => vec![(points[0], points[1])],
+        n => (0..n).map(|i| (points[i], points[(i + 1) % n])).collect(),
+    }
+}
+
+fn project(points: &[Vec2], axis: Vec2) -> (f64, f64) {
+    points.iter().fold((::std::f64::INFINITY, ::std::f64::NEG_INFINITY), |(min, max), &point| {
+        let projection = point.dot(axis);
+        (min.min(projection), max.max(projection))
+    })
+}
+
+// Whether the point is inside a polygon or on its edges.
+fn inside(polygon: &[Vec2], point: Vec2) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+
+    let sides: Vec<f64> = edges(polygon).into_iter()
+        .map(|(start, end)| (end - start).cross(point - start))
+        .collect();
+
+    sides.iter().all(|&side| side >= 0.) || sides.iter().all(|&side| side <= 0.)
+}
+
+fn cores_overlap(core: &[Vec2], other: &[Vec2]) -> bool {
+    // convex shapes overlap when their edges cross or one is inside the other
+    inside(other, core[0]) || inside(core, other[0]) ||
+    edges(core).into_iter().any(|(start, end)| {
+        edges(other).into_iter().any(|(other_start, other_end)| {
+            segments_cross(start, end, other_start, other_end)
+        })
+    })
+}
+
+fn segments_cross(start: Vec2, end: Vec2, other_start: Vec2, other_end: Vec2) -> bool {
+    let dir = end - start;
+    let other_dir = other_end - other_start;
+    let denominator = dir.cross(other_dir);
+
+    if denominator == 0. {
+        // parallel, they only touch where an end lies on the other segment
+        return closest_on_segment(other_start, start, end) == other_start ||
+               closest_on_segment(other_end, start, end) == other_end ||
+               closest_on_segment(start, other_start, other_end) == start;
+    }
+
+    let t = (other_start - start).cross(other_dir) / denominator;
+    let u = (other_start - start).cross(dir) / denominator;
+    t >= 0. && t <= 1. && u >= 0. && u <= 1.
+}
+
+fn closest_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let dir = end - start;
+    let length_squared = dir.length_squared();
+
+    if length_squared == 0. {
+        return start;
+    }
+
+    let t = ((point - start).dot(dir) / length_squared).max(0.).min(1.);
+    start + dir * t
+}
+
+// The closest points of two cores that don't overlap, which always include an
+// end of one of the segments around them.
+fn closest_points(core: &[Vec2], other: &[Vec2]) -> (Vec2, Vec2) {
+    let mut best = (core[0], other[0]);
+    let mut best_distance = ::std::f64::INFINITY;
+
+    for (start, end) in edges(core) {
+        for (other_start, other_end) in edges(other) {
+            let candidates = [
+                (start, closest_on_segment(start, other_start, other_end)),
+                (end, closest_on_segment(end, other_start, other_end)),
+                (closest_on_segment(other_start, start, end), other_start),
+                (closest_on_segment(other_end, start, end), other_end),
+            ];
+
+            for &(point, other_point) in candidates.iter() {
+                let distance = (other_point - point).length_squared();
+                if distance < best_distance {
+                    best = (point, other_point);
+                    best_distance = distance;
+                }
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::{Rng, SeedableRng, XorShiftRng};
+
+    // random cases checked per property, always the same ones
+    const CASES: usize = 2000;
+    const TOUCHING: f64 = 1e-6;
+
+    fn point(rng: &mut XorShiftRng) -> Vec2 {
+        Vec2::new(rng.gen_range(-20., 20.), rng.gen_range(-20., 20.))
+    }
+
+    fn shape(rng: &mut XorShiftRng) -> Shape {
+        match rng.gen_range(0, 4) {
+            0 => Shape::from(Circle {
+                center: point(rng),
+                radius: rng.gen_range(0.5, 10.),
+            }),
+            1 => Shape::from(Capsule {
+                start: point(rng),
+                end: point(rng),
+                radius: rng.gen_range(0.5, 5.),
+            }),
+            2 => Shape::from(Rectangle {
+                x: rng.gen_range(-20., 20.),
+                y: rng.gen_range(-20., 20.),
+                w: rng.gen_range(1., 15.),
+                h: rng.gen_range(1., 15.),
+            }),
+            _ => {
+                // points on a circle are always convex
+                let center = point(rng);
+                let radius = rng.gen_range(2., 12.);
+                let mut angles: Vec<f64> = (0..rng.gen_range(3, 8)).map(|_| rng.gen_range(0., 2. * ::std::f64::consts::PI)).collect();
+                angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                angles.dedup_by(|a, b| *a - *b < 0.05);
+
+                let points = angles.iter().map(|&angle| center + Vec2::from_angle(angle) * radius).collect();
+                match Polygon::new(points) {
+                    Some(polygon) => Shape::from(polygon),
+                    None => Shape::from(Circle { center: center, radius: radius }),
+                }
+            },
+        }
+    }
+
+    fn pairs() -> Vec<(Shape, Shape)> {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        (0..CASES).map(|_| (shape(&mut rng), shape(&mut rng))).collect()
+    }
+
+    #[test]
+    fn overlaps_is_symmetric() {
+        for (a, b) in pairs() {
+            assert_eq!(a.overlaps(&b), b.overlaps(&a), "{:?} and {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn penetration_leaves_shapes_touching() {
+        let mut overlapping = 0;
+
+        for (a, b) in pairs() {
+            if let Some(push) = a.penetration(&b) {
+                overlapping += 1;
+
+                assert!(push.length() > 0., "{:?} and {:?}", a, b);
+                assert!(a.translate(push).distance(&b) < TOUCHING, "{:?} pushed by {:?} from {:?}", a, push, b);
+                assert!(a.translate(push * 0.99).overlaps(&b), "{:?} pushed too far by {:?} from {:?}", a, push, b);
+                assert!(!a.translate(push * 1.01).overlaps(&b), "{:?} not pushed far enough by {:?} from {:?}", a, push, b);
+            }
+        }
+
+        // the random shapes must actually exercise the property
+        assert!(overlapping > CASES / 10);
+    }
+
+    #[test]
+    fn penetration_is_opposite_for_the_other_shape() {
+        for (a, b) in pairs() {
+            match (a.penetration(&b), b.penetration(&a)) {
+                (Some(push), Some(other_push)) => assert!((push.length() - other_push.length()).abs() < 1e-6 &&
+                                                          push.dot(other_push) < 0., "{:?} and {:?}", a, b),
+                (None, None) => {},
+                pushes => panic!("{:?} for {:?} and {:?}", pushes, a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn distance_is_zero_only_when_overlapping() {
+        for (a, b) in pairs() {
+            let distance = a.distance(&b);
+
+            if a.overlaps(&b) {
+                assert_eq!(distance, 0., "{:?} and {:?}", a, b);
+            } else {
+                assert!(distance >= 0., "{:?} and {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn sweep_finds_first_contact() {
+        const SAMPLES: usize = 200;
+        let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+        let mut hits = 0;
+
+        for (a, b) in pairs() {
+            let motion = Vec2::new(rng.gen_range(-60., 60.), rng.gen_range(-60., 60.));
+            let at = |t: f64| a.translate(motion * t);
+
+            // the first sampled point of the motion clearly inside `b`
+            let deep_hit = (0..SAMPLES + 1)
+                .map(|i_sample| i_sample as f64 / SAMPLES as f64)
+                .find(|&t| at(t).penetration(&b).map(|push| push.length() > 0.5).unwrap_or(false));
+
+            match a.sweep(motion, &b) {
+                Some(t) => {
+                    hits += 1;
+                    assert!(t >= 0. && t <= 1.);
+                    assert!(at(t).distance(&b) < SWEEP_EPSILON, "{:?} moving by {:?} to {:?}", a, motion, b);
+                    assert!(deep_hit.map(|deep_t| t <= deep_t).unwrap_or(true), "{:?} moving by {:?} to {:?}", a, motion, b);
+
+                    // nothing overlaps before the contact
+                    for i_sample in 0..SAMPLES {
+                        let sample_t = i_sample as f64 / SAMPLES as f64;
+                        if sample_t < t {
+                            assert!(!at(sample_t).overlaps(&b), "{:?} moving by {:?} to {:?}", a, motion, b);
+                        }
+                    }
+                },
+                None => assert!(deep_hit.is_none(), "{:?} moving by {:?} missed {:?}", a, motion, b),
+            }
+        }
+
+        assert!(hits > CASES / 10);
+    }
+
+    #[test]
+    fn polygons_must_be_convex() {
+        let square = vec![Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.), Vec2::new(0., 1.)];
+        assert!(Polygon::new(square.clone()).is_some());
+        assert!(Polygon::new(square.into_iter().rev().collect()).is_some());
+
+        let dart = vec![Vec2::new(0., 0.), Vec2::new(2., 1.), Vec2::new(0., 2.), Vec2::new(1., 1.)];
+        assert!(Polygon::new(dart).is_none());
+
+        let line = vec![Vec2::new(0., 0.), Vec2::new(1., 1.), Vec2::new(2., 2.)];
+        assert!(Polygon::new(line).is_none());
+
+        let pentagon: Vec<Vec2> = (0..5).map(|i| Vec2::from_angle(i as f64 * 2. * ::std::f64::consts::PI / 5.)).collect();
+        assert!(Polygon::new(pentagon.clone()).is_some());
+        let pentagram = [0, 2, 4, 1, 3].iter().map(|&i| pentagon[i]).collect();
+        assert!(Polygon::new(pentagram).is_none());
+    }
+}
@@ -1,17 +1,77 @@
 macro_rules! struct_events {
     (
-        keyboard: {
-            $( $k_alias:ident : $k_sdl:ident ),*
+        actions: {
+            $( $k_alias:ident : $k_action:ident = [ $( $k_sdl:ident ),* ] [ $( $k_btn:ident ),* ] ),*
+        },
+        axes: {
+            $( $a_axis:ident : $a_neg:ident / $a_pos:ident ),*
         },
         else: {
             $( $e_alias:ident : $e_sdl:pat ),*
         }
     ) => {
 
-        use ::sdl2::EventPump;
+        use ::sdl2::{EventPump, GameControllerSubsystem};
+        use ::sdl2::controller::{Axis, Button, GameController};
+        use ::sdl2::keyboard::{Keycode, TextInputUtil};
+        use ::std::collections::HashSet;
+        use ::phi::bindings::Bindings;
+        use ::phi::replay::{Recorder, Replay, escape, unescape};
+
+        // Stick positions closer to the center than this count as centered.
+        const DEAD_ZONE: f64 = 0.25;
+        // How far a stick must be pushed to also hold the digital action.
+        const DIGITAL_THRESHOLD: f64 = 0.5;
+        // SDL_TOUCH_MOUSEID, mouse events SDL synthesizes from touches, which
+        // are handled as touches instead.
+        const TOUCH_MOUSE_ID: u32 = 0xFFFFFFFF;
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Action {
+            $( $k_action ),*
+        }
+
+        impl Action {
+            pub fn all() -> &'static [Action] {
+                &[ $( Action::$k_action ),* ]
+            }
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $( Action::$k_action => stringify!($k_alias) ),*
+                }
+            }
+
+            pub fn default_keys(self) -> Vec<Keycode> {
+                match self {
+                    $( Action::$k_action => vec![ $( Keycode::$k_sdl ),* ] ),*
+                }
+            }
+
+            pub fn default_buttons(self) -> Vec<Button> {
+                match self {
+                    $( Action::$k_action => vec![ $( Button::$k_btn ),* ] ),*
+                }
+            }
+        }
         
         pub struct ImmediateEvents {
             resize: Option<(u32, u32)>,
+            // any key or controller button pressed during the tick, bound or not
+            pub key_pressed: Option<Keycode>,
+            pub button_pressed: Option<Button>,
+            pub mouse_moved: bool,
+            pub mouse_left: Option<bool>,
+            pub mouse_middle: Option<bool>,
+            pub mouse_right: Option<bool>,
+            pub wheel: (i32, i32),
+            // only filled in while text input is started
+            pub text: String,
+            // text being composed by an input method, and the cursor in it
+            pub text_editing: Option<(String, i32)>,
+            // backspace presses including key repeats, for deleting text
+            pub text_backspace: u32,
+            pub text_enter: bool,
             $( pub $k_alias: Option<bool>, )*
             $( pub $e_alias: bool ),*
         }
@@ -20,6 +80,17 @@ macro_rules! struct_events {
             pub fn new() -> ImmediateEvents {
                 ImmediateEvents {
                     resize: None,
+                    key_pressed: None,
+                    button_pressed: None,
+                    mouse_moved: false,
+                    mouse_left: None,
+                    mouse_middle: None,
+                    mouse_right: None,
+                    wheel: (0, 0),
+                    text: String::new(),
+                    text_editing: None,
+                    text_backspace: 0,
+                    text_enter: false,
                     $( $k_alias: None, )*
                     $( $e_alias: false ),*
                 }
@@ -27,67 +98,623 @@ macro_rules! struct_events {
         }
 
         pub struct Events {
-            pump: EventPump,
+            pump: Option<EventPump>,
+            recorder: Option<Recorder>,
+            replay: Option<Replay>,
+            // saved with recordings, so that replays get the same random numbers
+            seed: u32,
+
+            controller_subsystem: Option<GameControllerSubsystem>,
+            controllers: Vec<GameController>,
+
+            text_input_util: Option<TextInputUtil>,
+            text_input: bool,
+
+            bindings: Bindings,
+            held_keys: HashSet<Keycode>,
+            held_buttons: HashSet<Button>,
+            // dead-zoned axis positions in [-1, 1]
+            axes: ::std::collections::HashMap<Axis, f64>,
+            // the finger currently acting as the pointer
+            finger: Option<i64>,
 
             pub now: ImmediateEvents,
 
+            // pointer position in logical pixels, touches move it and hold the
+            // left button
+            pub mouse: (f64, f64),
+            pub mouse_left: bool,
+            pub mouse_middle: bool,
+            pub mouse_right: bool,
+
             $( pub $k_alias: bool ),*
         }
 
 
         impl Events {
             pub fn new(pump: EventPump) -> Events {
+                let seed = ::std::time::SystemTime::now()
+                    .duration_since(::std::time::UNIX_EPOCH)
+                    .map(|time| time.as_secs() as u32 ^ time.subsec_nanos())
+                    .unwrap_or(1);
+
+                Events::with_pump(Some(pump), seed)
+            }
+
+            // Without an event pump, input only comes from `feed`. The seed is
+            // fixed, so that headless runs are reproducible.
+            pub fn headless() -> Events {
+                Events::with_pump(None, 1)
+            }
+
+            fn with_pump(pump: Option<EventPump>, seed: u32) -> Events {
                 Events {
                     pump: pump,
+                    recorder: None,
+                    replay: None,
+                    seed: seed.max(1),
+
+                    controller_subsystem: None,
+                    controllers: Vec::new(),
+
+                    text_input_util: None,
+                    text_input: false,
+
+                    bindings: Bindings::new(),
+                    held_keys: HashSet::new(),
+                    held_buttons: HashSet::new(),
+                    axes: ::std::collections::HashMap::new(),
+                    finger: None,
 
                     now: ImmediateEvents::new(),
 
+                    mouse: (0., 0.),
+                    mouse_left: false,
+                    mouse_middle: false,
+                    mouse_right: false,
+
                     $( $k_alias: false ),*
                 }
             }
 
+            // Controllers are opened as SDL reports them plugged in, which
+            // includes the ones already connected at startup.
+            pub fn enable_controllers(&mut self, subsystem: GameControllerSubsystem) {
+                self.controller_subsystem = Some(subsystem);
+            }
+
+            pub fn enable_text_input(&mut self, util: TextInputUtil) {
+                // SDL starts with text input on, only views asking for it get it
+                util.stop();
+                self.text_input_util = Some(util);
+            }
+
+            // Shows an on-screen keyboard where there is one, and starts
+            // filling `now.text` and the other text fields.
+            pub fn start_text_input(&mut self) {
+                if let Some(ref util) = self.text_input_util {
+                    util.start();
+                }
+                self.text_input = true;
+            }
+
+            pub fn stop_text_input(&mut self) {
+                if let Some(ref util) = self.text_input_util {
+                    util.stop();
+                }
+                self.text_input = false;
+            }
+
+            pub fn bindings(&self) -> &Bindings {
+                &self.bindings
+            }
+
+            pub fn set_bindings(&mut self, bindings: Bindings) {
+                self.bindings = bindings;
+
+                // inputs held down right now may start or stop holding an action
+                $( self.$k_alias = self.is_held(Action::$k_action); )*
+            }
+
+            pub fn axis(&self, axis: Axis) -> f64 {
+                self.axes.get(&axis).cloned().unwrap_or(0.)
+            }
+
+            // Every following tick is appended to the replay file at `path`.
+            pub fn start_recording(&mut self, path: &str) -> Result<(), String> {
+                self.recorder = Some(Recorder::create(path, self.seed)?);
+                Ok(())
+            }
+
+            // Following ticks take their input from the replay file at `path`
+            // instead of the event pump, until the file runs out.
+            pub fn start_replay(&mut self, path: &str) -> Result<(), String> {
+                let replay = Replay::open(path)?;
+                self.seed = replay.seed().max(1);
+                self.replay = Some(replay);
+                Ok(())
+            }
+
+            // A seed for a random number generator. Views must take their
+            // randomness from here for replays to play out the same way.
+            pub fn next_seed(&mut self) -> [u32; 4] {
+                let mut seed = [0; 4];
+
+                // xorshift, never reaches 0 from a non-zero state
+                for part in seed.iter_mut() {
+                    self.seed ^= self.seed << 13;
+                    self.seed ^= self.seed >> 17;
+                    self.seed ^= self.seed << 5;
+                    *part = self.seed;
+                }
+
+                seed
+            }
+
             pub fn pump(&mut self, renderer: &mut ::sdl2::render::Renderer) {
+                let events: Vec<::sdl2::event::Event> = match self.pump {
+                    Some(ref mut pump) => pump.poll_iter().collect(),
+                    None => Vec::new(),
+                };
+
+                self.pump_events(events, renderer);
+            }
+
+            // Like `pump`, for events that don't come from the event pump,
+            // e.g. those scripted in headless runs. They are ignored, except
+            // for `Quit`, while a replay is running.
+            pub fn pump_events(&mut self, events: Vec<::sdl2::event::Event>, renderer: &mut ::sdl2::render::Renderer) {
+                match self.replay.as_mut().and_then(|replay| replay.next_tick()) {
+                    Some(tokens) => {
+                        if let Err(err) = self.load_tick_tokens(&tokens) {
+                            println!("Stopping replay: {}", err);
+                            self.replay = None;
+                        }
+
+                        // the window can still be closed while replaying
+                        for event in events {
+                            if let ::sdl2::event::Event::Quit { .. } = event {
+                                self.now.quit = true;
+                            }
+                        }
+                    },
+                    None => {
+                        if self.replay.take().is_some() {
+                            println!("Replay finished");
+                        }
+
+                        self.feed(events, renderer);
+                    },
+                }
+
+                if self.recorder.is_some() {
+                    let tokens = self.tick_tokens();
+
+                    if let Err(err) = self.recorder.as_mut().unwrap().write_tick(&tokens) {
+                        println!("Stopping recording: {}", err);
+                        self.recorder = None;
+                    }
+                }
+            }
+
+            // Held actions are written by name, actions pressed or released
+            // during the tick as `+name` or `-name`, other events as `!name`.
+            pub fn tick_tokens(&self) -> Vec<String> {
+                let mut tokens = Vec::new();
+
+                if let Some((w, h)) = self.now.resize {
+                    tokens.push(format!("resize={}x{}", w, h));
+                }
+
+                if let Some(key) = self.now.key_pressed {
+                    // key names may contain spaces, which separate the tokens
+                    tokens.push(format!("key={}", escape(&key.name())));
+                }
+
+                if let Some(button) = self.now.button_pressed {
+                    tokens.push(format!("button={}", button.string()));
+                }
+
+                tokens.push(format!("mouse={},{}", self.mouse.0, self.mouse.1));
+
+                if self.now.mouse_moved {
+                    tokens.push("!mouse_moved".to_string());
+                }
+
+                if self.now.wheel != (0, 0) {
+                    tokens.push(format!("wheel={},{}", self.now.wheel.0, self.now.wheel.1));
+                }
+
+                for &(name, held, now) in &[
+                    ("mouse_left", self.mouse_left, self.now.mouse_left),
+                    ("mouse_middle", self.mouse_middle, self.now.mouse_middle),
+                    ("mouse_right", self.mouse_right, self.now.mouse_right),
+                ] {
+                    if held {
+                        tokens.push(name.to_string());
+                    }
+
+                    match now {
+                        Some(true) => tokens.push(format!("+{}", name)),
+                        Some(false) => tokens.push(format!("-{}", name)),
+                        None => {},
+                    }
+                }
+
+                if !self.now.text.is_empty() {
+                    tokens.push(format!("text={}", escape(&self.now.text)));
+                }
+
+                if self.now.text_backspace > 0 {
+                    tokens.push(format!("backspace={}", self.now.text_backspace));
+                }
+
+                if self.now.text_enter {
+                    tokens.push("!text_enter".to_string());
+                }
+
+                for (axis, value) in &self.axes {
+                    if *value != 0. {
+                        tokens.push(format!("axis:{}={}", axis.string(), value));
+                    }
+                }
+
+                $(
+                    if self.$k_alias {
+                        tokens.push(stringify!($k_alias).to_string());
+                    }
+
+                    match self.now.$k_alias {
+                        Some(true) => tokens.push(format!("+{}", stringify!($k_alias))),
+                        Some(false) => tokens.push(format!("-{}", stringify!($k_alias))),
+                        None => {},
+                    }
+                )*
+
+                $(
+                    if self.now.$e_alias {
+                        tokens.push(format!("!{}", stringify!($e_alias)));
+                    }
+                )*
+
+                tokens
+            }
+
+            pub fn load_tick_tokens(&mut self, tokens: &[String]) -> Result<(), String> {
                 self.now = ImmediateEvents::new();
+                self.held_keys.clear();
+                self.held_buttons.clear();
+                self.axes.clear();
+                self.finger = None;
+                self.mouse_left = false;
+                self.mouse_middle = false;
+                self.mouse_right = false;
+                $( self.$k_alias = false; )*
 
-                for event in self.pump.poll_iter() {
-                    use ::sdl2::event::Event::*;
-                    use ::sdl2::event::WindowEventId::Resized;
-                    use ::sdl2::keyboard::Keycode::*;
-                    
-                    match event {
-                        Window { win_event_id: Resized, .. } => {
-                            self.now.resize = Some(renderer.output_size().unwrap());
-                        },
-                        KeyDown { keycode, .. } => match keycode {
-                            $(
-                                Some($k_sdl) => {
-                                    if !self.$k_alias {
-                                        self.now.$k_alias = Some(true);
-                                    }
-                                    self.$k_alias = true;
-                                }
-                            ),*
-                            _ => {}
-                        },
-
-                        KeyUp { keycode, .. } => match keycode {
-                            $(
-                                Some($k_sdl) => {
-                                    self.now.$k_alias = Some(false);
-                                    self.$k_alias = false;
-                                }
-                            ),*
-                            _ => {}
-                        },
+                for token in tokens {
+                    let invalid = || format!("invalid token {}", token);
 
+                    if token.starts_with("resize=") {
+                        let size: Vec<u32> = token["resize=".len()..].split('x')
+                            .filter_map(|n| n.parse().ok()).collect();
+
+                        if size.len() != 2 {
+                            return Err(invalid());
+                        }
+
+                        self.now.resize = Some((size[0], size[1]));
+                        continue;
+                    }
+
+                    if token.starts_with("key=") {
+                        let name = unescape(&token["key=".len()..]);
+                        self.now.key_pressed = Some(Keycode::from_name(&name).ok_or_else(&invalid)?);
+                        continue;
+                    }
+
+                    if token.starts_with("button=") {
+                        let name = &token["button=".len()..];
+                        self.now.button_pressed = Some(Button::from_string(name).ok_or_else(&invalid)?);
+                        continue;
+                    }
+
+                    if token.starts_with("mouse=") || token.starts_with("wheel=") {
+                        let coords: Vec<f64> = token[6..].split(',')
+                            .filter_map(|n| n.parse().ok()).collect();
+
+                        if coords.len() != 2 {
+                            return Err(invalid());
+                        }
+
+                        if token.starts_with("mouse=") {
+                            self.mouse = (coords[0], coords[1]);
+                        } else {
+                            self.now.wheel = (coords[0] as i32, coords[1] as i32);
+                        }
+                        continue;
+                    }
+
+                    if token.starts_with("text=") {
+                        self.now.text = unescape(&token["text=".len()..]);
+                        continue;
+                    }
+
+                    if token.starts_with("backspace=") {
+                        self.now.text_backspace = token["backspace=".len()..].parse().map_err(|_| invalid())?;
+                        continue;
+                    }
+
+                    if token.starts_with("axis:") {
+                        let mut parts = token["axis:".len()..].splitn(2, '=');
+                        let axis = parts.next().and_then(Axis::from_string).ok_or_else(&invalid)?;
+                        let value = parts.next().and_then(|v| v.parse().ok()).ok_or_else(&invalid)?;
+                        self.axes.insert(axis, value);
+                        continue;
+                    }
+
+                    match &token[..] {
+                        "!mouse_moved" => self.now.mouse_moved = true,
+                        "!text_enter" => self.now.text_enter = true,
+                        "mouse_left" => self.mouse_left = true,
+                        "+mouse_left" => self.now.mouse_left = Some(true),
+                        "-mouse_left" => self.now.mouse_left = Some(false),
+                        "mouse_middle" => self.mouse_middle = true,
+                        "+mouse_middle" => self.now.mouse_middle = Some(true),
+                        "-mouse_middle" => self.now.mouse_middle = Some(false),
+                        "mouse_right" => self.mouse_right = true,
+                        "+mouse_right" => self.now.mouse_right = Some(true),
+                        "-mouse_right" => self.now.mouse_right = Some(false),
+                        $(
+                            t if t == stringify!($k_alias) => self.$k_alias = true,
+                            t if t == concat!("+", stringify!($k_alias)) => self.now.$k_alias = Some(true),
+                            t if t == concat!("-", stringify!($k_alias)) => self.now.$k_alias = Some(false),
+                        )*
                         $(
-                            $e_sdl => { self.now.$e_alias = true; }
-                        ),*
-                        _ => {},
+                            t if t == concat!("!", stringify!($e_alias)) => self.now.$e_alias = true,
+                        )*
+                        _ => return Err(invalid()),
                     }
                 }
+
+                Ok(())
+            }
+
+            fn feed(&mut self, events: Vec<::sdl2::event::Event>, renderer: &mut ::sdl2::render::Renderer) {
+                self.now = ImmediateEvents::new();
+
+                for event in events {
+                    self.handle(event, renderer);
+                }
+            }
+
+            fn handle(&mut self, event: ::sdl2::event::Event, renderer: &mut ::sdl2::render::Renderer) {
+                use ::sdl2::event::Event::*;
+                use ::sdl2::event::WindowEventId::Resized;
+
+                match event {
+                    Window { win_event_id: Resized, .. } => {
+                        self.now.resize = Some(renderer.output_size().unwrap());
+                    },
+
+                    KeyDown { keycode: Some(keycode), .. } => {
+                        if self.text_input {
+                            match keycode {
+                                Keycode::Backspace => self.now.text_backspace += 1,
+                                Keycode::Return | Keycode::KpEnter => self.now.text_enter = true,
+                                _ => {},
+                            }
+                        }
+
+                        // ignore key repeats
+                        if self.held_keys.insert(keycode) {
+                            self.now.key_pressed = Some(keycode);
+
+                            for action in self.bindings.actions(keycode) {
+                                self.refresh(action);
+                            }
+                        }
+                    },
+
+                    KeyUp { keycode: Some(keycode), .. } => {
+                        self.held_keys.remove(&keycode);
+
+                        for action in self.bindings.actions(keycode) {
+                            self.refresh(action);
+                        }
+                    },
+
+                    TextInput { text, .. } => {
+                        if self.text_input {
+                            self.now.text.push_str(&text);
+                        }
+                    },
+
+                    TextEditing { text, start, .. } => {
+                        if self.text_input {
+                            self.now.text_editing = Some((text, start));
+                        }
+                    },
+
+                    MouseMotion { which, x, y, .. } if which != TOUCH_MOUSE_ID => {
+                        self.move_mouse(renderer, x as f64, y as f64);
+                    },
+
+                    MouseButtonDown { which, mouse_btn, x, y, .. } if which != TOUCH_MOUSE_ID => {
+                        self.move_mouse(renderer, x as f64, y as f64);
+                        self.set_mouse_button(mouse_btn, true);
+                    },
+
+                    MouseButtonUp { which, mouse_btn, x, y, .. } if which != TOUCH_MOUSE_ID => {
+                        self.move_mouse(renderer, x as f64, y as f64);
+                        self.set_mouse_button(mouse_btn, false);
+                    },
+
+                    MouseWheel { which, x, y, .. } if which != TOUCH_MOUSE_ID => {
+                        self.now.wheel.0 += x;
+                        self.now.wheel.1 += y;
+                    },
+
+                    // touch coordinates are normalized to [0, 1]
+                    FingerDown { finger_id, x, y, .. } => {
+                        if self.finger.is_none() {
+                            let (w, h) = renderer.output_size().unwrap();
+                            self.finger = Some(finger_id);
+                            self.move_mouse(renderer, x as f64 * w as f64, y as f64 * h as f64);
+                            self.set_mouse_button(::sdl2::mouse::Mouse::Left, true);
+                        }
+                    },
+
+                    FingerMotion { finger_id, x, y, .. } => {
+                        if self.finger == Some(finger_id) {
+                            let (w, h) = renderer.output_size().unwrap();
+                            self.move_mouse(renderer, x as f64 * w as f64, y as f64 * h as f64);
+                        }
+                    },
+
+                    FingerUp { finger_id, x, y, .. } => {
+                        if self.finger == Some(finger_id) {
+                            let (w, h) = renderer.output_size().unwrap();
+                            self.finger = None;
+                            self.move_mouse(renderer, x as f64 * w as f64, y as f64 * h as f64);
+                            self.set_mouse_button(::sdl2::mouse::Mouse::Left, false);
+                        }
+                    },
+
+                    ControllerDeviceAdded { which, .. } => {
+                        let controller = self.controller_subsystem.as_ref()
+                            .map(|subsystem| subsystem.open(which as u32));
+
+                        match controller {
+                            Some(Ok(controller)) => {
+                                println!("Controller connected: {}", controller.name());
+                                self.controllers.push(controller);
+                            },
+                            Some(Err(err)) => println!("Could not open controller {}: {:?}", which, err),
+                            None => {},
+                        }
+                    },
+
+                    ControllerDeviceRemoved { .. } => {
+                        self.controllers.retain(|controller| controller.attached());
+
+                        // whatever the unplugged controller held is released
+                        self.held_buttons.clear();
+                        self.axes.clear();
+                        for &action in Action::all() {
+                            self.refresh(action);
+                        }
+                    },
+
+                    ControllerButtonDown { button, .. } => {
+                        if self.held_buttons.insert(button) {
+                            self.now.button_pressed = Some(button);
+
+                            for action in self.bindings.button_actions(button) {
+                                self.refresh(action);
+                            }
+                        }
+                    },
+
+                    ControllerButtonUp { button, .. } => {
+                        self.held_buttons.remove(&button);
+
+                        for action in self.bindings.button_actions(button) {
+                            self.refresh(action);
+                        }
+                    },
+
+                    ControllerAxisMotion { axis, value, .. } => {
+                        let value = (value as f64 / 32767.).max(-1.).min(1.);
+                        let value =
+                            if value.abs() < DEAD_ZONE { 0. }
+                            else { value.signum() * (value.abs() - DEAD_ZONE) / (1. - DEAD_ZONE) };
+
+                        self.axes.insert(axis, value);
+
+                        $(
+                            if axis == Axis::$a_axis {
+                                self.refresh(Action::$a_neg);
+                                self.refresh(Action::$a_pos);
+                            }
+                        )*
+                    },
+
+                    $(
+                        $e_sdl => { self.now.$e_alias = true; }
+                    ),*
+                    _ => {},
+                }
+            }
+
+            // Takes window pixels, the mouse is kept in the logical ones views
+            // draw in, see `Phi::set_resolution`.
+            fn move_mouse(&mut self, renderer: &::sdl2::render::Renderer, x: f64, y: f64) {
+                let (scale_x, scale_y) = renderer.scale();
+                let viewport = renderer.viewport();
+                let x = x / scale_x as f64 - viewport.x() as f64;
+                let y = y / scale_y as f64 - viewport.y() as f64;
+
+                if self.mouse != (x, y) {
+                    self.mouse = (x, y);
+                    self.now.mouse_moved = true;
+                }
+            }
+
+            fn set_mouse_button(&mut self, button: ::sdl2::mouse::Mouse, down: bool) {
+                use ::sdl2::mouse::Mouse;
+
+                let (held, now) = match button {
+                    Mouse::Left => (&mut self.mouse_left, &mut self.now.mouse_left),
+                    Mouse::Middle => (&mut self.mouse_middle, &mut self.now.mouse_middle),
+                    Mouse::Right => (&mut self.mouse_right, &mut self.now.mouse_right),
+                    _ => return,
+                };
+
+                if *held != down {
+                    *now = Some(down);
+                }
+                *held = down;
+            }
+
+            // Whether the action is held other than by a stick pushed past
+            // `DIGITAL_THRESHOLD`. Held actions are replayed without their
+            // inputs, so the stick's position is what tells them apart.
+            pub fn held_digitally(&self, action: Action) -> bool {
+                let held = match action {
+                    $( Action::$k_action => self.$k_alias ),*
+                };
+
+                held && !self.held_by_axis(action)
+            }
+
+            fn held_by_axis(&self, action: Action) -> bool {
+                false $(
+                    || (action == Action::$a_neg && self.axis(Axis::$a_axis) <= -DIGITAL_THRESHOLD)
+                    || (action == Action::$a_pos && self.axis(Axis::$a_axis) >= DIGITAL_THRESHOLD)
+                )*
+            }
+
+            fn is_held(&self, action: Action) -> bool {
+                self.held_by_axis(action) ||
+                self.bindings.keys(action).iter().any(|key| self.held_keys.contains(key)) ||
+                self.bindings.buttons(action).iter().any(|button| self.held_buttons.contains(button))
+            }
+
+            // Updates the action's state after one of its inputs changed.
+            fn refresh(&mut self, action: Action) {
+                let held = self.is_held(action);
+
+                match action {
+                    $(
+                        Action::$k_action => {
+                            if held != self.$k_alias {
+                                self.now.$k_alias = Some(held);
+                            }
+                            self.$k_alias = held;
+                        }
+                    ),*
+                }
             }
         }
     }
 }
-
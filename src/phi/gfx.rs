@@ -1,9 +1,14 @@
+use ::phi::camera::Camera;
 use ::phi::data::Rectangle;
 use ::std::cell::RefCell;
+use ::std::collections::HashMap;
+use ::std::fs::File;
+use ::std::io::Read;
 use ::std::path::Path;
 use ::std::rc::Rc;
-use ::sdl2::render::{Renderer, Texture};
-use ::sdl2_image::LoadTexture;
+use ::sdl2::pixels::Color;
+use ::sdl2::rect::Point;
+use ::sdl2::render::{BlendMode, Renderer, Texture};
 
 #[derive(Clone)]
 pub struct Sprite {
@@ -26,8 +31,10 @@ impl Sprite {
         }
     }
 
-    pub fn load(renderer: &Renderer, path: &str) -> Option<Sprite> {
-        renderer.load_texture(Path::new(path)).ok().map(Sprite::new)
+    // Swaps the texture behind this sprite and every other one cut from it,
+    // their regions stay the same.
+    pub fn replace_texture(&self, texture: Texture) {
+        *self.tex.borrow_mut() = texture;
     }
 
     pub fn region(&self, rect: Rectangle) -> Option<Sprite> {
@@ -50,21 +57,527 @@ impl Sprite {
         }
     }
 
+    // Cuts the first `count` frames of `frame_w` x `frame_h` out of the
+    // sprite, left to right and then top to bottom. None if it is too small.
+    pub fn grid(&self, frame_w: f64, frame_h: f64, count: usize) -> Option<Vec<Sprite>> {
+        let wide = (self.src.w / frame_w) as usize;
+        if wide == 0 {
+            return None;
+        }
+
+        (0..count).map(|i_frame| self.region(Rectangle {
+            x: frame_w * (i_frame % wide) as f64,
+            y: frame_h * (i_frame / wide) as f64,
+            w: frame_w,
+            h: frame_h,
+        })).collect()
+    }
+
     pub fn size(&self) -> (f64, f64) {
         (self.src.w, self.src.h)
     }
+}
+
+// How to draw a sprite besides where, the default draws it as it is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawParams {
+    // degrees clockwise around `pivot`
+    pub angle: f64,
+    // relative to the top-left corner of the destination, None for its center
+    pub pivot: Option<(f64, f64)>,
+    // flips happen before the rotation
+    pub flip_h: bool,
+    pub flip_v: bool,
+    // multiplied with the sprite's colors, white keeps them
+    pub tint: Color,
+    pub alpha: u8,
+    // None keeps the texture's own mode
+    pub blend: Option<BlendMode>,
+}
+
+impl Default for DrawParams {
+    fn default() -> DrawParams {
+        DrawParams {
+            angle: 0.,
+            pivot: None,
+            flip_h: false,
+            flip_v: false,
+            tint: Color::RGB(255, 255, 255),
+            alpha: 255,
+            blend: None,
+        }
+    }
+}
+
+impl DrawParams {
+    // These params further tinted, used by sprites drawing other sprites in
+    // a color of their own.
+    fn tinted(&self, tint: Color, alpha: u8) -> DrawParams {
+        let (r, g, b) = self.tint.rgb();
+        let (tint_r, tint_g, tint_b) = tint.rgb();
+        let mul = |a: u8, b: u8| (a as u16 * b as u16 / 255) as u8;
+
+        DrawParams {
+            tint: Color::RGB(mul(r, tint_r), mul(g, tint_g), mul(b, tint_b)),
+            alpha: mul(self.alpha, alpha),
+            ..*self
+        }
+    }
+}
+
+pub trait Renderable {
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams);
+
+    fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
+        self.render_ex(renderer, dest, &DrawParams::default());
+    }
+}
+
+impl Renderable for Sprite {
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams) {
+        let mut tex = self.tex.borrow_mut();
+
+        if *params == DrawParams::default() {
+            renderer.copy(&mut tex, self.src.to_sdl(), dest.to_sdl());
+            return;
+        }
+
+        // the texture is shared with other sprites, which expect it unchanged
+        let old_color = tex.color_mod();
+        let old_alpha = tex.alpha_mod();
+        let old_blend = tex.blend_mode();
+
+        let (r, g, b) = params.tint.rgb();
+        tex.set_color_mod(r, g, b);
+        tex.set_alpha_mod(params.alpha);
+        if let Some(blend) = params.blend {
+            tex.set_blend_mode(blend);
+        }
+
+        let center = params.pivot.map(|(x, y)| Point::new(x as i32, y as i32));
+        renderer.copy_ex(&tex, self.src.to_sdl(), dest.to_sdl(), params.angle, center, (params.flip_h, params.flip_v));
+
+        tex.set_color_mod(old_color.0, old_color.1, old_color.2);
+        tex.set_alpha_mod(old_alpha);
+        tex.set_blend_mode(old_blend);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayMode {
+    Loop,
+    // stops on the last frame
+    Once,
+    // goes back and forth between the first and last frames
+    PingPong,
+}
+
+#[derive(Clone)]
+pub struct AnimatedSprite {
+    frames: Rc<Vec<Sprite>>,
+    // seconds each frame is shown
+    durations: Vec<f64>,
+    mode: PlayMode,
+
+    current: usize,
+    // seconds the current frame has been shown
+    current_time: f64,
+    backwards: bool,
+    finished: bool,
+}
+
+impl AnimatedSprite {
+    // Every frame is shown for `frame_duration` seconds, looping.
+    pub fn new(frames: Vec<Sprite>, frame_duration: f64) -> AnimatedSprite {
+        let durations = vec![frame_duration; frames.len()];
+        AnimatedSprite::with_durations(frames, durations)
+    }
+
+    pub fn with_durations(frames: Vec<Sprite>, durations: Vec<f64>) -> AnimatedSprite {
+        assert!(!frames.is_empty() && frames.len() == durations.len());
+        assert!(durations.iter().all(|&duration| duration > 0.));
+
+        AnimatedSprite {
+            frames: Rc::new(frames),
+            durations: durations,
+            mode: PlayMode::Loop,
+
+            current: 0,
+            current_time: 0.,
+            backwards: false,
+            finished: false,
+        }
+    }
+
+    // Frames laid out in a grid on a spritesheet, see `Sprite::grid`. None
+    // without any frame, like `from_regions`.
+    pub fn from_grid(sheet: &Sprite, frame_w: f64, frame_h: f64, count: usize, frame_duration: f64) -> Option<AnimatedSprite> {
+        sheet.grid(frame_w, frame_h, count)
+            .and_then(|frames| AnimatedSprite::non_empty(frames, frame_duration))
+    }
+
+    pub fn from_regions(sheet: &Sprite, regions: &[Rectangle], frame_duration: f64) -> Option<AnimatedSprite> {
+        regions.iter().map(|&region| sheet.region(region)).collect::<Option<Vec<_>>>()
+            .and_then(|frames| AnimatedSprite::non_empty(frames, frame_duration))
+    }
+
+    fn non_empty(frames: Vec<Sprite>, frame_duration: f64) -> Option<AnimatedSprite> {
+        if frames.is_empty() { None }
+        else { Some(AnimatedSprite::new(frames, frame_duration)) }
+    }
+
+    pub fn set_mode(&mut self, mode: PlayMode) {
+        self.mode = mode;
+    }
+
+    pub fn set_frame_duration(&mut self, frame_duration: f64) {
+        assert!(frame_duration > 0.);
+
+        for duration in self.durations.iter_mut() {
+            *duration = frame_duration;
+        }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.current
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Jumps to the start of frame `index`, e.g. to pick frames by hand.
+    pub fn set_frame(&mut self, index: usize) {
+        assert!(index < self.frames.len());
+
+        self.current = index;
+        self.current_time = 0.;
+        self.finished = false;
+    }
+
+    pub fn restart(&mut self) {
+        self.set_frame(0);
+        self.backwards = false;
+    }
+
+    // Whether a `PlayMode::Once` animation has shown its last frame in full.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn sprite(&self) -> &Sprite {
+        &self.frames[self.current]
+    }
+
+    pub fn size(&self) -> (f64, f64) {
+        self.sprite().size()
+    }
+
+    // Moves the animation `elapsed` seconds forward, skipping frames if
+    // needed.
+    pub fn update(&mut self, elapsed: f64) {
+        self.current_time += elapsed;
+
+        while !self.finished && self.current_time >= self.durations[self.current] {
+            self.current_time -= self.durations[self.current];
+            self.next_frame();
+        }
+    }
+
+    fn next_frame(&mut self) {
+        let last = self.frames.len() - 1;
+
+        match self.mode {
+            PlayMode::Loop => {
+                self.current = if self.current >= last { 0 } else { self.current + 1 };
+            },
+            PlayMode::Once => {
+                if self.current >= last {
+                    self.finished = true;
+                } else {
+                    self.current += 1;
+                }
+            },
+            PlayMode::PingPong => {
+                if last == 0 {
+                    return;
+                }
+
+                if self.current >= last {
+                    self.backwards = true;
+                } else if self.current == 0 {
+                    self.backwards = false;
+                }
+
+                self.current = if self.backwards { self.current - 1 } else { self.current + 1 };
+            },
+        }
+    }
+}
 
-    pub fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
-        renderer.copy(&mut self.tex.borrow_mut(), self.src.to_sdl(), dest.to_sdl());
+impl Renderable for AnimatedSprite {
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams) {
+        self.sprite().render_ex(renderer, dest, params);
     }
 }
 
-pub trait CopySprite {
-    fn copy_sprite(&mut self, sprite: &Sprite, dest: Rectangle);
+// The `_world` variants take `dest` in world coordinates, drawing it where
+// `camera` shows it.
+pub trait CopySprite<T> {
+    fn copy_sprite(&mut self, sprite: &T, dest: Rectangle);
+    fn copy_sprite_ex(&mut self, sprite: &T, dest: Rectangle, params: &DrawParams);
+    fn copy_sprite_world(&mut self, camera: &Camera, sprite: &T, dest: Rectangle);
+    fn copy_sprite_world_ex(&mut self, camera: &Camera, sprite: &T, dest: Rectangle, params: &DrawParams);
 }
 
-impl<'window> CopySprite for Renderer<'window> {
-    fn copy_sprite(&mut self, sprite: &Sprite, dest:Rectangle) {
+impl<'window, T: Renderable> CopySprite<T> for Renderer<'window> {
+    fn copy_sprite(&mut self, sprite: &T, dest: Rectangle) {
         sprite.render(self, dest);
     }
+
+    fn copy_sprite_ex(&mut self, sprite: &T, dest: Rectangle, params: &DrawParams) {
+        sprite.render_ex(self, dest, params);
+    }
+
+    fn copy_sprite_world(&mut self, camera: &Camera, sprite: &T, dest: Rectangle) {
+        sprite.render(self, camera.to_screen(dest));
+    }
+
+    fn copy_sprite_world_ex(&mut self, camera: &Camera, sprite: &T, dest: Rectangle, params: &DrawParams) {
+        let zoom = camera.zoom();
+        sprite.render_ex(self, camera.to_screen(dest), &DrawParams {
+            pivot: params.pivot.map(|(x, y)| (x * zoom, y * zoom)),
+            ..*params
+        });
+    }
+}
+
+struct Glyph {
+    // None for glyphs without any pixels, e.g. spaces
+    sprite: Option<Sprite>,
+    offset: (f64, f64),
+    advance: f64,
+}
+
+// A font drawn from glyphs cut out of images, read from an AngelCode BMFont
+// descriptor in the text format.
+pub struct BitmapFont {
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f64>,
+    // the size the glyphs were drawn for
+    size: f64,
+    line_height: f64,
+}
+
+impl BitmapFont {
+    // `load_page` gets the image files named in the descriptor.
+    pub fn load<F>(path: &Path, mut load_page: F) -> Result<BitmapFont, String>
+    where F: FnMut(&str) -> Result<Sprite, String> {
+        let mut text = String::new();
+        File::open(path).and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("could not read bitmap font {}: {}", path.display(), err))?;
+
+        let mut font = BitmapFont {
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+            size: 0.,
+            line_height: 0.,
+        };
+
+        let mut pages = HashMap::new();
+
+        for (i_line, line) in text.lines().enumerate() {
+            let invalid = |what: &str| format!("invalid bitmap font {} line {}: {}", path.display(), i_line + 1, what);
+            let (tag, attrs) = parse_bmfont_line(line);
+
+            let number = |key: &str| attrs.get(key).and_then(|value| value.parse::<f64>().ok())
+                .ok_or_else(|| invalid(&format!("{} needs a number {}", tag, key)));
+            let character = |key: &str| number(key).and_then(|id| ::std::char::from_u32(id as u32)
+                .ok_or_else(|| invalid(&format!("{} is not a character", id))));
+
+            match tag {
+                // sizes are negative for fonts matching the character height
+                "info" => font.size = number("size")?.abs(),
+                "common" => font.line_height = number("lineHeight")?,
+                "page" => {
+                    let file = attrs.get("file").ok_or_else(|| invalid("page needs a file"))?;
+                    pages.insert(number("id")? as u32, load_page(file)?);
+                },
+                "char" => {
+                    let page = pages.get(&(number("page")? as u32))
+                        .ok_or_else(|| invalid("char on an unknown page"))?;
+
+                    let rect = Rectangle {
+                        x: number("x")?,
+                        y: number("y")?,
+                        w: number("width")?,
+                        h: number("height")?,
+                    };
+
+                    let sprite =
+                        if rect.w == 0. || rect.h == 0. { None }
+                        else { Some(page.region(rect).ok_or_else(|| invalid("char outside of its page"))?) };
+
+                    font.glyphs.insert(character("id")?, Glyph {
+                        sprite: sprite,
+                        offset: (number("xoffset")?, number("yoffset")?),
+                        advance: number("xadvance")?,
+                    });
+                },
+                "kerning" => {
+                    font.kernings.insert((character("first")?, character("second")?), number("amount")?);
+                },
+                _ => {},
+            }
+        }
+
+        if font.size <= 0. || font.line_height <= 0. {
+            return Err(format!("invalid bitmap font {}: missing info or common line", path.display()));
+        }
+
+        Ok(font)
+    }
+
+    // Lays out a single line of text at `size`, scaling the glyphs from the
+    // size they were drawn for. Characters the font lacks are drawn as '?'
+    // when it has one.
+    pub fn text(&self, text: &str, size: i32, color: Color) -> BitmapText {
+        let mut glyphs = Vec::new();
+        let mut x = 0.;
+        let mut previous = None;
+
+        for c in text.chars() {
+            let (c, glyph) = match self.glyphs.get(&c) {
+                Some(glyph) => (c, glyph),
+                None => match self.glyphs.get(&'?') {
+                    Some(glyph) => ('?', glyph),
+                    None => continue,
+                },
+            };
+
+            if let Some(previous) = previous {
+                x += self.kernings.get(&(previous, c)).cloned().unwrap_or(0.);
+            }
+
+            if let Some(ref sprite) = glyph.sprite {
+                let (w, h) = sprite.size();
+                glyphs.push((sprite.clone(), Rectangle {
+                    x: x + glyph.offset.0,
+                    y: glyph.offset.1,
+                    w: w,
+                    h: h,
+                }));
+            }
+
+            x += glyph.advance;
+            previous = Some(c);
+        }
+
+        BitmapText {
+            glyphs: glyphs,
+            color: color,
+            scale: size as f64 / self.size,
+            w: x,
+            h: self.line_height,
+        }
+    }
+}
+
+// Splits a line like `char id=65 x=0 letter="A"` into its tag and attributes.
+fn parse_bmfont_line(line: &str) -> (&str, HashMap<&str, &str>) {
+    let line = line.trim();
+    let tag_end = line.find(' ').unwrap_or(line.len());
+    let mut attrs = HashMap::new();
+    let mut rest = line[tag_end..].trim_start();
+
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let after = &rest[eq + 1..];
+
+        let (value, next) =
+            if after.starts_with('"') {
+                let end = after[1..].find('"').map(|i| i + 1).unwrap_or(after.len());
+                (&after[1..end], &after[(end + 1).min(after.len())..])
+            } else {
+                let end = after.find(' ').unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            };
+
+        attrs.insert(key, value);
+        rest = next.trim_start();
+    }
+
+    (&line[..tag_end], attrs)
+}
+
+// A line of text from a `BitmapFont`, drawn glyph by glyph and tinted in its
+// color. Glyph images should be white for the tint to show as is.
+#[derive(Clone)]
+pub struct BitmapText {
+    // glyphs and where they go at the font's own size
+    glyphs: Vec<(Sprite, Rectangle)>,
+    color: Color,
+    scale: f64,
+    w: f64,
+    h: f64,
+}
+
+impl BitmapText {
+    pub fn size(&self) -> (f64, f64) {
+        (self.w * self.scale, self.h * self.scale)
+    }
+}
+
+impl Renderable for BitmapText {
+    // Flips and rotations apply to the whole text rather than to each glyph.
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams) {
+        if self.w == 0. {
+            return;
+        }
+
+        let scale_x = dest.w / self.w;
+        let scale_y = dest.h / self.h;
+        let (pivot_x, pivot_y) = params.pivot.unwrap_or((dest.w / 2., dest.h / 2.));
+        let mut glyph_params = params.tinted(self.color, 255);
+
+        for &(ref sprite, rect) in &self.glyphs {
+            let w = rect.w * scale_x;
+            let h = rect.h * scale_y;
+            let x = if params.flip_h { dest.w - rect.x * scale_x - w } else { rect.x * scale_x };
+            let y = if params.flip_v { dest.h - rect.y * scale_y - h } else { rect.y * scale_y };
+
+            glyph_params.pivot = Some((pivot_x - x, pivot_y - y));
+            sprite.render_ex(renderer, Rectangle {
+                x: dest.x + x,
+                y: dest.y + y,
+                w: w,
+                h: h,
+            }, &glyph_params);
+        }
+    }
+}
+
+// Text from either a TrueType or a bitmap font, see `Phi::str_sprite`.
+#[derive(Clone)]
+pub enum TextSprite {
+    Ttf(Sprite),
+    Bitmap(BitmapText),
+}
+
+impl TextSprite {
+    pub fn size(&self) -> (f64, f64) {
+        match *self {
+            TextSprite::Ttf(ref sprite) => sprite.size(),
+            TextSprite::Bitmap(ref text) => text.size(),
+        }
+    }
+}
+
+impl Renderable for TextSprite {
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams) {
+        match *self {
+            TextSprite::Ttf(ref sprite) => sprite.render_ex(renderer, dest, params),
+            TextSprite::Bitmap(ref text) => text.render_ex(renderer, dest, params),
+        }
+    }
 }
@@ -0,0 +1,116 @@
+use ::phi::{Phi, Events, View, ViewStack};
+use ::phi::settings::Settings;
+use ::sdl2::event::Event;
+use ::sdl2::pixels::{Color, PixelFormatEnum};
+use ::sdl2::render::Renderer;
+use ::sdl2::surface::Surface;
+use ::std::path::Path;
+
+// Drives views through a software renderer drawing into an offscreen surface,
+// one fixed update and one frame per `step`, so no window or display is needed.
+pub struct Headless {
+    context: Phi<'static>,
+    views: ViewStack,
+    dt: f64,
+
+    size: (u32, u32),
+    // last frame as packed RGB24
+    frame: Vec<u8>,
+
+    _ttf_context: ::sdl2_ttf::Sdl2TtfContext,
+}
+
+impl Headless {
+    pub fn new<F>(width: u32, height: u32, tick_rate: u32, init: F) -> Headless
+    where F: Fn(&mut Phi) -> Box<View> {
+        assert!(tick_rate > 0);
+
+        let ttf_context = ::sdl2_ttf::init().unwrap();
+        let surface = Surface::new(width, height, PixelFormatEnum::ARGB8888).unwrap();
+
+        let mut context = Phi::new(
+            Events::headless(),
+            Renderer::from_surface(surface).unwrap(),
+            // the player's own settings would make runs unreproducible
+            Settings::new(),
+            None
+            );
+
+        let root = init(&mut context);
+        let views = ViewStack::new(&mut context, root);
+
+        Headless {
+            context: context,
+            views: views,
+            dt: 1. / tick_rate as f64,
+
+            size: (width, height),
+            frame: vec![0; (width * height * 3) as usize],
+
+            _ttf_context: ttf_context,
+        }
+    }
+
+    pub fn context(&mut self) -> &mut Phi<'static> {
+        &mut self.context
+    }
+
+    pub fn views(&self) -> &ViewStack {
+        &self.views
+    }
+
+    // Returns false once the last view has been popped.
+    pub fn step(&mut self, events: Vec<Event>) -> bool {
+        self.context.events.pump_events(events, &mut self.context.renderer);
+        self.views.update(&mut self.context, self.dt);
+
+        if self.views.is_empty() {
+            return false;
+        }
+
+        self.context.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        self.context.renderer.clear();
+        // draw the state right after the update, nothing to interpolate here
+        self.views.draw(&mut self.context, 1.);
+        self.frame = self.context.renderer.read_pixels(None, PixelFormatEnum::RGB24).unwrap();
+
+        true
+    }
+
+    // Steps without any input, returns how many frames were actually run.
+    pub fn run(&mut self, frames: u32) -> u32 {
+        for frame in 0..frames {
+            if !self.step(Vec::new()) {
+                return frame;
+            }
+        }
+
+        frames
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        assert!(x < self.size.0 && y < self.size.1);
+
+        let i = ((y * self.size.0 + x) * 3) as usize;
+        Color::RGB(self.frame[i], self.frame[i + 1], self.frame[i + 2])
+    }
+
+    pub fn save_frame(&mut self, path: &str) -> Result<(), String> {
+        let (w, h) = self.size;
+        let surface = Surface::from_data(&mut self.frame, w, h, w * 3, PixelFormatEnum::RGB24)
+            .map_err(|err| format!("{:?}", err))?;
+
+        surface.save_bmp(Path::new(path))
+            .map_err(|err| format!("could not save frame to {}: {:?}", path, err))
+    }
+}
+
+// SDL isn't thread-safe, tests using it hold this to run one at a time.
+#[cfg(test)]
+static SDL: ::std::sync::Mutex<()> = ::std::sync::Mutex::new(());
+
+#[cfg(test)]
+pub fn lock_sdl() -> ::std::sync::MutexGuard<'static, ()> {
+    // poisoned by a failed test, which leaves nothing behind for the others
+    SDL.lock().unwrap_or_else(|err| err.into_inner())
+}
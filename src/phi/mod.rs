@@ -1,62 +1,253 @@
-use self::gfx::Sprite;
+use self::assets::Assets;
+use self::atlas::Atlas;
+use self::audio::{Audio, Sound};
+use self::data::{Rectangle, Vec2};
+use self::gfx::{CopySprite, Sprite, TextSprite};
+use self::settings::Settings;
+use self::text::{TextLayout, TextStyle};
+use self::window::{Fullscreen, Scaling, WindowConfig};
 use ::sdl2::render::Renderer;
 use ::sdl2::pixels::Color;
-use ::std::collections::HashMap;
-use ::std::path::Path;
 
 #[macro_use]
 mod events;
 pub mod gfx;
 pub mod data;
+pub mod assets;
+pub mod atlas;
+pub mod audio;
+pub mod camera;
+pub mod bindings;
+pub mod headless;
+pub mod replay;
+pub mod settings;
+pub mod text;
+pub mod window;
 
 struct_events!{
-    keyboard: {
-        key_escape: Escape,
-        key_up: Up,
-        key_down: Down,
-        key_space: Space,
-        key_left: Left,
-        key_right: Right,
-        key_return: Return
+    actions: {
+        move_up: MoveUp = [Up, W] [DPadUp],
+        move_down: MoveDown = [Down, S] [DPadDown],
+        move_left: MoveLeft = [Left, A] [DPadLeft],
+        move_right: MoveRight = [Right, D] [DPadRight],
+        fire: Fire = [Space] [A, RightShoulder],
+        switch_weapon: SwitchWeapon = [Q, Tab] [Y],
+        pause: Pause = [Escape, P] [Start],
+        confirm: Confirm = [Return, Space] [A, Start],
+        cancel: Cancel = [Escape] [B, Back],
+        fullscreen: ToggleFullscreen = [F11] []
+    },
+    axes: {
+        LeftX: MoveLeft / MoveRight,
+        LeftY: MoveUp / MoveDown
     },
     else: {
         quit: Quit { .. }
     }
 }
 
+const SETTINGS_PATH: &'static str = "settings.ini";
+
+impl Events {
+    // Direction the player wants to move in, at most 1 long. Keys and the
+    // D-pad win over the left stick, which allows moving slower than full
+    // speed in any direction.
+    pub fn movement(&self) -> Vec2 {
+        let held = |action| self.held_digitally(action) as i8;
+        let digital = Vec2::new(
+            (held(Action::MoveRight) - held(Action::MoveLeft)) as f64,
+            (held(Action::MoveDown) - held(Action::MoveUp)) as f64);
+
+        let direction =
+            if digital != Vec2::default() { digital }
+            else { Vec2::new(self.axis(Axis::LeftX), self.axis(Axis::LeftY)) };
+
+        direction.clamp_length(1.)
+    }
+}
+
 pub struct Phi<'window> {
     pub events: Events,
     pub renderer: Renderer<'window>,
-    
-    cached_fonts: HashMap<(&'static str, i32), ::sdl2_ttf::Font>,
+    pub audio: Audio,
+    pub assets: Assets,
+    pub settings: Settings,
+
+    // the size views lay themselves out in, whatever the window's
+    resolution: (f64, f64),
+    scaling: Scaling,
+    // how the window was opened, None without one, e.g. in headless runs
+    window: Option<WindowConfig>,
+
+    // None when the settings shouldn't be persisted, e.g. in headless runs
+    settings_path: Option<&'static str>,
 }
 
 impl<'window> Phi<'window> {
-    fn new(events: Events, renderer: Renderer<'window>) -> Phi<'window> {
+    fn new(mut events: Events, renderer: Renderer<'window>, settings: Settings, settings_path: Option<&'static str>) -> Phi<'window> {
         ::sdl2_image::init(::sdl2_image::INIT_PNG);
 
+        events.set_bindings(Bindings::from_settings(&settings));
+        // the window's size at first, see `set_resolution`
+        let (w, h) = renderer.output_size().unwrap();
+
         Phi {
             events: events,
             renderer: renderer,
+            audio: Audio::silent(),
+            assets: Assets::new(),
+            settings: settings,
+
+            resolution: (w as f64, h as f64),
+            scaling: Scaling::Fit,
+            window: None,
+
+            settings_path: settings_path,
+        }
+    }
 
-            cached_fonts: HashMap::new(),
+    pub fn save_settings(&self) {
+        if let Some(path) = self.settings_path {
+            if let Err(err) = self.settings.save(path) {
+                println!("{}", err);
+            }
         }
     }
 
+    // The logical resolution, not the window's size.
     pub fn output_size(&self) -> (f64, f64) {
-        let (w, h) = self.renderer.output_size().unwrap();
-        (w as f64, h as f64)
+        self.resolution
+    }
+
+    pub fn set_resolution(&mut self, w: u32, h: u32) {
+        self.resolution = (w as f64, h as f64);
+        self.fit_resolution();
+    }
+
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        self.scaling = scaling;
+        self.fit_resolution();
+    }
+
+    pub fn fullscreen(&self) -> Option<Fullscreen> {
+        self.window.as_ref().map(|config| config.fullscreen)
+    }
+
+    // Also saved to the settings, for the next start.
+    pub fn set_fullscreen(&mut self, fullscreen: Fullscreen) -> Result<(), String> {
+        self.renderer.window_mut()
+            .ok_or("no window to make fullscreen".to_string())?
+            .set_fullscreen(fullscreen.to_sdl())
+            .map_err(|err| format!("could not switch to {}: {:?}", fullscreen.name(), err))?;
+
+        if let Some(ref mut config) = self.window {
+            config.fullscreen = fullscreen;
+            config.write_settings(&mut self.settings);
+        }
+
+        self.save_settings();
+        self.fit_resolution();
+        Ok(())
+    }
+
+    // Switches between a window and covering the desktop.
+    pub fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        let fullscreen = match self.fullscreen() {
+            Some(Fullscreen::Windowed) => Fullscreen::Desktop,
+            _ => Fullscreen::Windowed,
+        };
+
+        self.set_fullscreen(fullscreen)
+    }
+
+    // Scales the logical resolution to the window and centers it. Called
+    // again whenever the window is resized.
+    fn fit_resolution(&mut self) {
+        let (win_w, win_h) = self.renderer.output_size().unwrap();
+        let (w, h) = self.resolution;
+
+        let fit = (win_w as f64 / w).min(win_h as f64 / h);
+        let scale = match self.scaling {
+            Scaling::Integer if fit >= 1. => fit.floor(),
+            _ => fit,
+        };
+
+        // the viewport is in logical pixels, so are the bars' sizes
+        let bar_w = (win_w as f64 / scale - w) / 2.;
+        let bar_h = (win_h as f64 / scale - h) / 2.;
+
+        self.renderer.set_scale(scale as f32, scale as f32);
+        self.renderer.set_viewport(::sdl2::rect::Rect::new(bar_w.round() as i32, bar_h.round() as i32, w as u32, h as u32).unwrap());
+    }
+
+    // The asset called `name`, see `Assets`.
+    pub fn texture(&mut self, name: &str) -> Result<Sprite, String> {
+        self.assets.texture(&self.renderer, name)
     }
 
-    pub fn ttf_str_sprite(&mut self, text: &str, font_path: &'static str, size: i32, color: Color) -> Option<Sprite> {
-        if let Some(font) = self.cached_fonts.get(&(font_path, size)) {
-            return font.render(text, ::sdl2_ttf::blended(color)).ok()
-                .and_then(|surface| self.renderer.create_texture_from_surface(&surface).ok()).map(Sprite::new);
+    pub fn sound(&mut self, name: &str) -> Result<Sound, String> {
+        self.assets.sound(&mut self.audio, name)
+    }
+
+    // Music is streamed from its file rather than kept with the other assets.
+    pub fn play_music(&mut self, name: &str, fade: f64) -> Result<(), String> {
+        let path = self.assets.path(name)?;
+        self.audio.play_music(&path, fade)
+    }
+
+    pub fn atlas(&mut self, name: &str) -> Result<Atlas, String> {
+        Atlas::load(&mut self.assets, &self.renderer, name)
+    }
+
+    // Rendered text is cached, so calling this every frame is fine.
+    pub fn ttf_str_sprite(&mut self, text: &str, font_name: &str, size: i32, color: Color) -> Result<Sprite, String> {
+        self.assets.text(&self.renderer, text, font_name, size, color)
+    }
+
+    // A line of text in either kind of font, bitmap fonts are told apart by
+    // their `.fnt` extension.
+    pub fn str_sprite(&mut self, text: &str, font_name: &str, size: i32, color: Color) -> Result<TextSprite, String> {
+        if font_name.ends_with(".fnt") {
+            let font = self.assets.bitmap_font(&self.renderer, font_name)?;
+            Ok(TextSprite::Bitmap(font.text(text, size, color)))
+        } else {
+            self.ttf_str_sprite(text, font_name, size, color).map(TextSprite::Ttf)
+        }
+    }
+
+    // Spans of text, each in its own color or the style's, as lines wrapped
+    // and aligned according to `style`.
+    pub fn layout_text(&mut self, spans: &[(&str, Option<Color>)], style: &TextStyle) -> Result<TextLayout, String> {
+        let font = self.assets.font(style.font, style.size)?;
+        ::phi::text::layout(&font, spans, style)
+    }
+
+    // Draws the runs of `layout` one by one, with its top-left corner at (x, y).
+    // Runs are cached like any other text.
+    pub fn draw_text(&mut self, layout: &TextLayout, style: &TextStyle, x: f64, y: f64) -> Result<(), String> {
+        for run in &layout.runs {
+            let sprite = self.ttf_str_sprite(&run.text, style.font, style.size, run.color)?;
+            let (w, h) = sprite.size();
+
+            self.renderer.copy_sprite(&sprite, Rectangle {
+                x: x + run.rect.x,
+                y: y + run.rect.y,
+                w: w,
+                h: h,
+            });
         }
 
-        let font = ::sdl2_ttf::Font::from_file(Path::new(font_path), size).ok().unwrap();
-        let surface = font.render(text, ::sdl2_ttf::blended(color)).ok().unwrap();
-        Some(Sprite::new(self.renderer.create_texture_from_surface(&surface).ok().unwrap()))
+        Ok(())
+    }
+
+    // The laid out text as a single sprite, for text drawn often.
+    pub fn text_sprite(&mut self, spans: &[(&str, Option<Color>)], style: &TextStyle) -> Result<Sprite, String> {
+        let font = self.assets.font(style.font, style.size)?;
+        let layout = ::phi::text::layout(&font, spans, style)?;
+        let surface = ::phi::text::render(&font, &layout)?;
+
+        self.renderer.create_texture_from_surface(&surface).map(Sprite::new)
+            .map_err(|err| format!("could not create a texture for text: {:?}", err))
     }
 }
 
@@ -69,7 +260,11 @@ impl<'window> Drop for Phi<'window> {
 pub enum ViewAction {
     None,
     Quit,
-    ChangeView(Box<View>),
+    Push(Box<View>),
+    Pop,
+    // pops every view but the first one, e.g. back to the main menu
+    PopToRoot,
+    Replace(Box<View>),
 }
 
 pub trait View {
@@ -77,38 +272,261 @@ pub trait View {
 
     fn pause(&mut self, _context: &mut Phi) {}
 
-    fn render(&mut self, context: &mut Phi, elapsed: f64) -> ViewAction;
+    // Called at a fixed rate, `dt` is always the same duration in seconds.
+    // Handles input and advances the state, must not draw anything.
+    fn update(&mut self, _context: &mut Phi, _dt: f64) -> ViewAction {
+        ViewAction::None
+    }
+
+    // Draws the current state onto an already cleared screen. `alpha` in [0, 1)
+    // is how far between the last two updates the frame is, used to interpolate
+    // positions so motion stays smooth at any frame rate.
+    fn draw(&self, _context: &mut Phi, _alpha: f64) {}
+
+    // Whether the view below this one on the stack is still drawn, for pause
+    // menus and dialogs shown over the running game. Only the top view updates.
+    fn draws_below(&self) -> bool {
+        false
+    }
+
+    // Name of the background music asset played while the view is on top,
+    // None keeps whatever is already playing.
+    fn music(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+// seconds over which tracks are cross-faded when the top view changes
+const MUSIC_FADE: f64 = 1.5;
+
+pub struct ViewStack {
+    views: Vec<Box<View>>,
+}
+
+impl ViewStack {
+    pub fn new(context: &mut Phi, mut root: Box<View>) -> ViewStack {
+        root.resume(context);
+
+        let stack = ViewStack {
+            views: vec![root],
+        };
+
+        stack.sync_music(context);
+        stack
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    pub fn update(&mut self, context: &mut Phi, dt: f64) {
+        let action = if context.events.now.quit {
+            ViewAction::Quit
+        } else {
+            match self.views.last_mut() {
+                Some(view) => view.update(context, dt),
+                None => return,
+            }
+        };
+
+        let changes_view = match action {
+            ViewAction::None | ViewAction::Quit => false,
+            _ => true,
+        };
+
+        self.apply(context, action);
+
+        if changes_view {
+            self.sync_music(context);
+        }
+    }
+
+    fn sync_music(&self, context: &mut Phi) {
+        if let Some(name) = self.views.last().and_then(|view| view.music()) {
+            if let Err(err) = context.play_music(name, MUSIC_FADE) {
+                println!("{}", err);
+            }
+        }
+    }
+
+    pub fn draw(&self, context: &mut Phi, alpha: f64) {
+        let first = self.views.iter()
+            .rposition(|view| !view.draws_below())
+            .unwrap_or(0);
+
+        let top = self.views.len().saturating_sub(1);
+
+        for (i_view, view) in self.views.iter().enumerate().skip(first) {
+            // views below the top one aren't updated, interpolating them
+            // would keep replaying their last tick
+            view.draw(context, if i_view == top { alpha } else { 1. });
+        }
+    }
+
+    fn apply(&mut self, context: &mut Phi, action: ViewAction) {
+        match action {
+            ViewAction::None => {},
+            ViewAction::Quit => {
+                if let Some(top) = self.views.last_mut() {
+                    top.pause(context);
+                }
+                self.views.clear();
+            },
+            ViewAction::Push(mut new_view) => {
+                if let Some(top) = self.views.last_mut() {
+                    top.pause(context);
+                }
+                new_view.resume(context);
+                self.views.push(new_view);
+            },
+            ViewAction::Pop => self.pop(context, 1),
+            ViewAction::PopToRoot => {
+                let count = self.views.len().saturating_sub(1);
+                self.pop(context, count);
+            },
+            ViewAction::Replace(mut new_view) => {
+                if let Some(mut top) = self.views.pop() {
+                    top.pause(context);
+                }
+                new_view.resume(context);
+                self.views.push(new_view);
+            },
+        }
+    }
+
+    fn pop(&mut self, context: &mut Phi, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        if let Some(mut top) = self.views.pop() {
+            top.pause(context);
+        }
+
+        for _ in 1..count {
+            self.views.pop();
+        }
+
+        if let Some(top) = self.views.last_mut() {
+            top.resume(context);
+        }
+    }
+}
+
+pub struct Timestep {
+    dt: f64,
+    max_ticks: u32,
+    accumulator: f64,
+}
+
+impl Timestep {
+    pub fn new(tick_rate: u32, max_ticks: u32) -> Timestep {
+        assert!(tick_rate > 0 && max_ticks > 0);
+
+        Timestep {
+            dt: 1. / tick_rate as f64,
+            max_ticks: max_ticks,
+            accumulator: 0.,
+        }
+    }
+
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    pub fn advance(&mut self, elapsed: f64) {
+        self.accumulator += elapsed;
+
+        // After a long hitch, drop the time we cannot catch up on instead of
+        // spiralling into ever longer frames.
+        let max_accumulated = self.dt * self.max_ticks as f64;
+        if self.accumulator > max_accumulated {
+            self.accumulator = max_accumulated;
+        }
+    }
+
+    pub fn tick(&mut self) -> bool {
+        if self.accumulator >= self.dt {
+            self.accumulator -= self.dt;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.dt
+    }
 }
 
-pub fn spawn<F>(title: &str, init: F)
+// `config` is overridden by the player's settings.
+pub fn spawn<F>(title: &str, tick_rate: u32, config: WindowConfig, init: F)
 where F: Fn(&mut Phi) -> Box<View> {
     let sdl_context = ::sdl2::init().unwrap();
     let video = sdl_context.video().unwrap();
     let mut timer = sdl_context.timer().unwrap();
+    let audio = sdl_context.audio();
     let _ttf_context = ::sdl2_ttf::init();
 
-    let window = video.window(title, 800, 600)
-        .position_centered().opengl().resizable()
-        .build().unwrap();
+    // the size the game asks for stays the logical resolution, whatever size
+    // the player picks for the window
+    let (width, height) = (config.width, config.height);
+    let settings = Settings::load_or_default(SETTINGS_PATH);
+    let config = config.with_settings(&settings);
+    let scaling = config.scaling;
+    let renderer = match config.open(&video, title) {
+        Ok(renderer) => renderer,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        },
+    };
+
+    let mut events = Events::new(sdl_context.event_pump().unwrap());
+    events.enable_text_input(video.text_input());
+    match sdl_context.game_controller() {
+        Ok(subsystem) => events.enable_controllers(subsystem),
+        Err(err) => println!("Game controllers unavailable: {:?}", err),
+    }
 
     let mut context = Phi::new(
-        Events::new(sdl_context.event_pump().unwrap()),
-        window.renderer().accelerated()
-            .build().unwrap()
+        events,
+        renderer,
+        settings,
+        Some(SETTINGS_PATH)
         );
+    context.window = Some(config);
+    context.set_scaling(scaling);
+    context.set_resolution(width, height);
+
+    match audio.map_err(|err| format!("{:?}", err)).and_then(|audio| Audio::open(&audio)) {
+        Ok(mut audio) => {
+            audio.apply_settings(&context.settings);
+            context.audio = audio;
+        },
+        Err(err) => println!("Playing without sound: {}", err),
+    }
 
-    let mut current_view = init(&mut context);
-    current_view.resume(&mut context);
+    // textures and fonts are reloaded as they are edited in debug builds
+    context.assets.watch(cfg!(debug_assertions));
+
+    let root = init(&mut context);
+    let mut views = ViewStack::new(&mut context, root);
+
+    let mut timestep = Timestep::new(tick_rate, 5);
 
     let interval = 1000/60;
     let mut before = timer.ticks();
     let mut last_second = timer.ticks();
     let mut fps = 0u16;
 
-    loop {
+    'running: loop {
         let now = timer.ticks();
         let dt = now - before;
-        let elapsed = dt as f64 / 1000.;
 
         if dt < interval {
             timer.delay(interval - dt);
@@ -124,20 +542,59 @@ where F: Fn(&mut Phi) -> Box<View> {
             fps = 0;
         }
 
-        context.events.pump(&mut context.renderer);
+        timestep.advance(dt as f64 / 1000.);
+        context.assets.update(&context.renderer, dt as f64 / 1000.);
 
-        match current_view.render(&mut context, elapsed) {
-            ViewAction::None => context.renderer.present(),
-            ViewAction::Quit => {
-                current_view.pause(&mut context);
-                break;
-            },
-            ViewAction::ChangeView(new_view) => {
-                current_view.pause(&mut context);
-                current_view = new_view;
-                current_view.resume(&mut context);
-            },
+        while timestep.tick() {
+            context.events.pump(&mut context.renderer);
+            if context.events.now.resize.is_some() {
+                context.fit_resolution();
+            }
+
+            if context.events.now.fullscreen == Some(true) {
+                if let Err(err) = context.toggle_fullscreen() {
+                    println!("{}", err);
+                }
+            }
+
+            views.update(&mut context, timestep.dt());
+
+            if views.is_empty() {
+                break 'running;
+            }
         }
+
+        context.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        context.renderer.clear();
+        views.draw(&mut context, timestep.alpha());
+        context.renderer.present();
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movement(tokens: &[&str]) -> Vec2 {
+        let mut events = Events::headless();
+        let tokens: Vec<String> = tokens.iter().map(|&token| token.to_string()).collect();
+        events.load_tick_tokens(&tokens).unwrap();
+        events.movement()
+    }
+
+    #[test]
+    fn stick_past_the_threshold_keeps_its_direction() {
+        // as recorded, the stick also holds the digital action
+        let direction = movement(&["axis:leftx=0.6", "axis:lefty=0.2", "move_right"]);
+        assert!((direction.x - 0.6).abs() < 1e-9 && (direction.y - 0.2).abs() < 1e-9, "{:?}", direction);
+    }
+
+    #[test]
+    fn keys_win_over_the_stick() {
+        let direction = movement(&["axis:leftx=0.3", "axis:lefty=0.2", "move_up"]);
+        assert_eq!(direction, Vec2::new(0., -1.));
+
+        let direction = movement(&["move_left", "move_down"]);
+        assert!((direction.length() - 1.).abs() < 1e-9 && direction.x < 0. && direction.y > 0.);
+    }
+}
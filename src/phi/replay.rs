@@ -0,0 +1,205 @@
+use ::std::collections::VecDeque;
+use ::std::fs::File;
+use ::std::io::{BufRead, BufReader, BufWriter, Write};
+
+const HEADER: &'static str = "arcade-rs replay v2";
+
+// A replay file has a header line and a `seed <n>` line for the random
+// numbers, followed by one line per update tick, holding the space-separated
+// tokens produced by `Events::tick_tokens`.
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &str, seed: u32) -> Result<Recorder, String> {
+        let file = File::create(path)
+            .map_err(|err| format!("could not create replay file {}: {}", path, err))?;
+
+        let mut recorder = Recorder {
+            out: BufWriter::new(file),
+        };
+
+        recorder.write_line(HEADER)
+            .and_then(|_| recorder.write_line(&format!("seed {}", seed)))
+            .map_err(|err| format!("could not write replay file {}: {}", path, err))?;
+
+        Ok(recorder)
+    }
+
+    pub fn write_tick(&mut self, tokens: &[String]) -> Result<(), String> {
+        let line = tokens.join(" ");
+        self.write_line(&line)
+            .map_err(|err| format!("could not write replay tick: {}", err))
+    }
+
+    fn write_line(&mut self, line: &str) -> ::std::io::Result<()> {
+        self.out.write_all(line.as_bytes())?;
+        self.out.write_all(b"\n")
+    }
+}
+
+pub struct Replay {
+    seed: u32,
+    ticks: VecDeque<Vec<String>>,
+}
+
+impl Replay {
+    pub fn open(path: &str) -> Result<Replay, String> {
+        let file = File::open(path)
+            .map_err(|err| format!("could not open replay file {}: {}", path, err))?;
+
+        let mut lines = BufReader::new(file).lines();
+
+        match lines.next() {
+            Some(Ok(ref header)) if header == HEADER => {},
+            _ => return Err(format!("{} is not a replay file", path)),
+        }
+
+        let seed = match lines.next() {
+            Some(Ok(ref line)) if line.starts_with("seed ") => line[5..].trim().parse().ok(),
+            _ => None,
+        }.ok_or(format!("missing seed in replay file {}", path))?;
+
+        let mut ticks = VecDeque::new();
+        for line in lines {
+            let line = line.map_err(|err| format!("could not read replay file {}: {}", path, err))?;
+            ticks.push_back(line.split_whitespace().map(String::from).collect());
+        }
+
+        Ok(Replay {
+            seed: seed,
+            ticks: ticks,
+        })
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn next_tick(&mut self) -> Option<Vec<String>> {
+        self.ticks.pop_front()
+    }
+}
+
+// Tokens are separated by whitespace, so it is percent-encoded in text.
+pub fn escape(text: &str) -> String {
+    escape_chars(text, "")
+}
+
+// Like `escape`, also encoding the given characters.
+pub fn escape_chars(text: &str, chars: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in text.chars() {
+        if c == '%' || c.is_whitespace() || chars.contains(c) {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+
+    escaped
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded =
+            if byte == b'%' && tail.len() >= 2 {
+                ::std::str::from_utf8(&tail[..2]).ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            } else {
+                None
+            };
+
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            },
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::phi::Events;
+
+    #[test]
+    fn escaped_text_has_no_whitespace_and_unescapes_back() {
+        for text in &["", "plain", "two words", "100% sure", "tab\tand\nnewline", "naïve café", "%41"] {
+            let escaped = escape(text);
+            assert!(!escaped.contains(char::is_whitespace), "{:?} escaped to {:?}", text, escaped);
+            assert_eq!(unescape(&escaped), *text);
+        }
+    }
+
+    #[test]
+    fn unescape_keeps_invalid_sequences() {
+        assert_eq!(unescape("%zz%4"), "%zz%4");
+        assert_eq!(unescape("50%"), "50%");
+    }
+
+    #[test]
+    fn tick_tokens_round_trip() {
+        let tokens: Vec<String> = vec![
+            "resize=1024x768",
+            "key=Left%20Shift",
+            "button=a",
+            "mouse=12.5,-3",
+            "!mouse_moved",
+            "wheel=0,-2",
+            "mouse_left",
+            "+mouse_left",
+            "-mouse_right",
+            "text=hello%20world",
+            "backspace=2",
+            "!text_enter",
+            "axis:leftx=0.75",
+            "move_up",
+            "+fire",
+            "fire",
+            "-pause",
+            "!quit",
+        ].into_iter().map(String::from).collect();
+
+        let mut events = Events::headless();
+        events.load_tick_tokens(&tokens).unwrap();
+
+        let mut loaded = events.tick_tokens();
+        let mut expected = tokens.clone();
+        loaded.sort();
+        expected.sort();
+        assert_eq!(loaded, expected);
+
+        // "_" is the name of a key, not an encoded space
+        events.load_tick_tokens(&["key=_".to_string()]).unwrap();
+        assert_eq!(events.tick_tokens(), vec!["key=_".to_string()]);
+
+        // loading a tick replaces everything the previous one held
+        events.load_tick_tokens(&["mouse=0,0".to_string()]).unwrap();
+        assert_eq!(events.tick_tokens(), vec!["mouse=0,0".to_string()]);
+    }
+
+    #[test]
+    fn invalid_tick_tokens_are_rejected() {
+        let mut events = Events::headless();
+
+        for token in &["resize=10", "key=NoSuchKey", "mouse=1", "axis:nope=1", "backspace=x", "unknown"] {
+            assert!(events.load_tick_tokens(&[token.to_string()]).is_err(), "{} was accepted", token);
+        }
+    }
+}
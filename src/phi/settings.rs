@@ -0,0 +1,110 @@
+use ::std::collections::BTreeMap;
+use ::std::fmt;
+use ::std::fs::File;
+use ::std::io::{Read, Write};
+
+// Plain `key = value` lines grouped under `[section]` headers, `#` starts a comment.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings::default()
+    }
+
+    pub fn load(path: &str) -> Result<Settings, String> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|err| format!("could not read settings file {}: {}", path, err))?;
+
+        Settings::parse(&contents)
+            .map_err(|err| format!("{} in settings file {}", err, path))
+    }
+
+    // A missing or broken settings file shouldn't stop the game from starting.
+    pub fn load_or_default(path: &str) -> Settings {
+        Settings::load(path).unwrap_or_else(|err| {
+            println!("Using default settings: {}", err);
+            Settings::new()
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<Settings, String> {
+        let mut settings = Settings::new();
+        let mut section = String::new();
+
+        for (i_line, line) in contents.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_string();
+                continue;
+            }
+
+            match line.find('=') {
+                Some(i_eq) => {
+                    let key = line[..i_eq].trim();
+                    let value = line[i_eq + 1..].trim();
+                    settings.set(&section, key, value);
+                },
+                None => return Err(format!("expected `key = value` on line {}", i_line + 1)),
+            }
+        }
+
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        File::create(path)
+            .and_then(|mut file| file.write_all(self.to_string().as_bytes()))
+            .map_err(|err| format!("could not write settings file {}: {}", path, err))
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.sections.get(section)
+            .and_then(|values| values.get(key))
+            .map(|value| &value[..])
+    }
+
+    // The keys and values of a section, ordered by key.
+    pub fn entries(&self, section: &str) -> Vec<(&str, &str)> {
+        self.sections.get(section)
+            .map(|values| values.iter().map(|(key, value)| (&key[..], &value[..])).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        self.sections.entry(section.to_string()).or_insert_with(BTreeMap::new)
+            .insert(key.to_string(), value.to_string());
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+
+        for (section, values) in &self.sections {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+
+            if !section.is_empty() {
+                writeln!(f, "[{}]", section)?;
+            }
+
+            for (key, value) in values {
+                writeln!(f, "{} = {}", key, value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
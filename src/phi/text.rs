@@ -0,0 +1,354 @@
+use ::phi::data::Rectangle;
+use ::sdl2::pixels::{Color, PixelFormatEnum};
+use ::sdl2::render::BlendMode;
+use ::sdl2::surface::Surface;
+use ::sdl2_ttf::Font;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug)]
+pub struct TextStyle {
+    pub font: &'static str,
+    pub size: i32,
+    // used for text without a color of its own
+    pub color: Color,
+    pub align: Align,
+    // lines are wrapped between words to fit, None only breaks them at '\n'
+    pub max_width: Option<f64>,
+    // distance between lines as a multiple of the one the font recommends
+    pub line_spacing: f64,
+}
+
+impl TextStyle {
+    pub fn new(font: &'static str, size: i32, color: Color) -> TextStyle {
+        TextStyle {
+            font: font,
+            size: size,
+            color: color,
+            align: Align::Left,
+            max_width: None,
+            line_spacing: 1.,
+        }
+    }
+}
+
+// A piece of a line drawn in one color, placed relative to the top-left
+// corner of the laid out text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub color: Color,
+    pub rect: Rectangle,
+}
+
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    pub runs: Vec<TextRun>,
+    pub w: f64,
+    pub h: f64,
+}
+
+// A word with the spaces following it, in a single color.
+struct Word {
+    text: String,
+    spaces: String,
+    color: Color,
+}
+
+// Splits colored spans into words, None marking the line breaks.
+fn words(spans: &[(&str, Option<Color>)], style: &TextStyle) -> Vec<Option<Word>> {
+    let mut words: Vec<Option<Word>> = Vec::new();
+
+    for &(text, color) in spans {
+        let color = color.unwrap_or(style.color);
+        let mut lines = text.split('\n');
+
+        if let Some(first) = lines.next() {
+            split_words(&mut words, first, color);
+        }
+
+        for line in lines {
+            words.push(None);
+            split_words(&mut words, line, color);
+        }
+    }
+
+    words
+}
+
+fn split_words(words: &mut Vec<Option<Word>>, text: &str, color: Color) {
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let word_end = rest.find(' ').unwrap_or(rest.len());
+        let spaces_end = rest[word_end..].find(|c| c != ' ').map(|i| word_end + i).unwrap_or(rest.len());
+
+        // spaces opening a span belong to the word before, whatever its color
+        if word_end == 0 {
+            if let Some(&mut Some(ref mut last)) = words.last_mut() {
+                last.spaces.push_str(&rest[..spaces_end]);
+                rest = &rest[spaces_end..];
+                continue;
+            }
+        }
+
+        words.push(Some(Word {
+            text: rest[..word_end].to_string(),
+            spaces: rest[word_end..spaces_end].to_string(),
+            color: color,
+        }));
+        rest = &rest[spaces_end..];
+    }
+}
+
+fn width(font: &Font, text: &str) -> Result<f64, String> {
+    if text.is_empty() {
+        return Ok(0.);
+    }
+
+    font.size(text).map(|(w, _)| w as f64)
+        .map_err(|err| format!("could not measure {:?}: {:?}", text, err))
+}
+
+// Lays `spans` out in lines, colored by the ones with a color and `style.color`
+// otherwise. Words longer than `style.max_width` get a line of their own and
+// stick out.
+pub fn layout(font: &Font, spans: &[(&str, Option<Color>)], style: &TextStyle) -> Result<TextLayout, String> {
+    let line_h = font.height() as f64;
+    let line_skip = font.line_skip() as f64 * style.line_spacing;
+
+    // each line as (text, color) pieces, and its width without trailing spaces
+    let mut lines: Vec<(Vec<(String, Color)>, f64)> = vec![(Vec::new(), 0.)];
+    // the width of the current line including the trailing spaces
+    let mut line_w = 0.;
+
+    for word in words(spans, style) {
+        let word = match word {
+            Some(word) => word,
+            None => {
+                lines.push((Vec::new(), 0.));
+                line_w = 0.;
+                continue;
+            },
+        };
+
+        let word_w = width(font, &word.text)?;
+        let spaces_w = width(font, &word.spaces)?;
+
+        let wraps = match style.max_width {
+            Some(max_width) => line_w > 0. && line_w + word_w > max_width,
+            None => false,
+        };
+
+        if wraps {
+            lines.push((Vec::new(), 0.));
+            line_w = 0.;
+        }
+
+        let line = lines.last_mut().unwrap();
+        line.1 = line_w + word_w;
+        line_w += word_w + spaces_w;
+
+        let text = word.text + &word.spaces;
+        match line.0.last_mut() {
+            Some(&mut (ref mut last_text, last_color)) if last_color == word.color => last_text.push_str(&text),
+            _ => line.0.push((text, word.color)),
+        }
+    }
+
+    let w = match style.max_width {
+        Some(max_width) => lines.iter().fold(max_width, |w, line| w.max(line.1)),
+        None => lines.iter().fold(0f64, |w, line| w.max(line.1)),
+    };
+
+    let h = (lines.len() - 1) as f64 * line_skip + line_h;
+
+    let mut runs = Vec::new();
+    for (i_line, (pieces, line_w)) in lines.into_iter().enumerate() {
+        let mut x = match style.align {
+            Align::Left => 0.,
+            Align::Center => ((w - line_w) / 2.).floor(),
+            Align::Right => w - line_w,
+        };
+
+        let y = (i_line as f64 * line_skip).floor();
+        let count = pieces.len();
+
+        for (i_piece, (mut text, color)) in pieces.into_iter().enumerate() {
+            if i_piece + 1 == count {
+                let trimmed_len = text.trim_end().len();
+                text.truncate(trimmed_len);
+            }
+
+            let run_w = width(font, &text)?;
+
+            // runs of spaces only move the next one
+            if !text.trim().is_empty() {
+                runs.push(TextRun {
+                    text: text,
+                    color: color,
+                    rect: Rectangle {
+                        x: x,
+                        y: y,
+                        w: run_w,
+                        h: line_h,
+                    },
+                });
+            }
+
+            x += run_w;
+        }
+    }
+
+    Ok(TextLayout {
+        runs: runs,
+        w: w,
+        h: h,
+    })
+}
+
+// Renders the whole layout onto a single transparent surface.
+pub fn render(font: &Font, layout: &TextLayout) -> Result<Surface<'static>, String> {
+    let mut surface = Surface::new((layout.w.ceil() as u32).max(1), (layout.h.ceil() as u32).max(1), PixelFormatEnum::ARGB8888)
+        .map_err(|err| format!("could not create a text surface: {:?}", err))?;
+
+    for run in &layout.runs {
+        let mut piece = font.render(&run.text[..], ::sdl2_ttf::blended(run.color))
+            .map_err(|err| format!("could not render {:?}: {:?}", run.text, err))?;
+
+        // runs never overlap, so their pixels are copied as they are instead
+        // of being blended with the transparent background
+        piece.set_blend_mode(BlendMode::None)
+            .and_then(|_| piece.blit(None, &mut surface, run.rect.to_sdl()))
+            .map_err(|err| format!("could not draw {:?}: {:?}", run.text, err))?;
+    }
+
+    Ok(surface)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::std::path::Path;
+
+    const RED: Color = Color::RGB(255, 0, 0);
+    const GRAY: Color = Color::RGB(128, 128, 128);
+
+    fn style() -> TextStyle {
+        TextStyle::new("belligerent.ttf", 20, GRAY)
+    }
+
+    // (text, spaces, color) of each word, None for the line breaks
+    fn split(spans: &[(&str, Option<Color>)]) -> Vec<Option<(String, String, Color)>> {
+        words(spans, &style()).into_iter()
+            .map(|word| word.map(|word| (word.text, word.spaces, word.color)))
+            .collect()
+    }
+
+    fn word(text: &str, spaces: &str, color: Color) -> Option<(String, String, Color)> {
+        Some((text.to_string(), spaces.to_string(), color))
+    }
+
+    fn with_font<F: FnOnce(&Font)>(f: F) {
+        let _lock = ::phi::headless::lock_sdl();
+        let _ttf_context = ::sdl2_ttf::init().unwrap();
+        let font = Font::from_file(Path::new("assets/belligerent.ttf"), 20).unwrap();
+        f(&font);
+    }
+
+    #[test]
+    fn words_keep_their_spaces() {
+        assert_eq!(split(&[("one  two three ", None)]), vec![
+            word("one", "  ", GRAY),
+            word("two", " ", GRAY),
+            word("three", " ", GRAY),
+        ]);
+        assert_eq!(split(&[("", None)]), vec![]);
+    }
+
+    #[test]
+    fn spaces_opening_a_span_belong_to_the_word_before() {
+        assert_eq!(split(&[("red", Some(RED)), ("  gray", None)]), vec![
+            word("red", "  ", RED),
+            word("gray", "", GRAY),
+        ]);
+
+        // unless there is none
+        assert_eq!(split(&[(" gray", None)]), vec![word("", " ", GRAY), word("gray", "", GRAY)]);
+    }
+
+    #[test]
+    fn newlines_break_lines() {
+        assert_eq!(split(&[("a\nb", None), ("\n\nc", Some(RED))]), vec![
+            word("a", "", GRAY),
+            None,
+            word("b", "", GRAY),
+            None,
+            None,
+            word("c", "", RED),
+        ]);
+    }
+
+    #[test]
+    fn lines_wrap_between_words() {
+        with_font(|font| {
+            let word_w = width(font, "word").unwrap();
+            let space_w = width(font, " ").unwrap();
+
+            let mut style = style();
+            // room for two words on a line, not three
+            style.max_width = Some(word_w * 2.5 + space_w * 2.);
+
+            let layout = layout(font, &[("word word word word word", None)], &style).unwrap();
+            let lines: Vec<f64> = layout.runs.iter().map(|run| run.rect.y).collect();
+            let texts: Vec<&str> = layout.runs.iter().map(|run| &run.text[..]).collect();
+
+            assert_eq!(texts, vec!["word word", "word word", "word"]);
+            assert!(lines[0] < lines[1] && lines[1] < lines[2]);
+            assert_eq!(layout.w, style.max_width.unwrap());
+        });
+    }
+
+    #[test]
+    fn long_words_stick_out() {
+        with_font(|font| {
+            let mut style = style();
+            style.max_width = Some(10.);
+
+            let layout = layout(font, &[("unbreakable", None)], &style).unwrap();
+
+            assert_eq!(layout.runs.len(), 1);
+            assert!(layout.w > 10.);
+            assert_eq!(layout.w, layout.runs[0].rect.w);
+        });
+    }
+
+    #[test]
+    fn colors_split_runs_and_align() {
+        with_font(|font| {
+            let mut style = style();
+            style.align = Align::Right;
+
+            let layout = layout(font, &[("short ", Some(RED)), ("and\na much longer line", None)], &style).unwrap();
+            let runs = &layout.runs;
+
+            assert_eq!(runs.len(), 3);
+            assert_eq!((&runs[0].text[..], runs[0].color), ("short ", RED));
+            assert_eq!((&runs[1].text[..], runs[1].color), ("and", GRAY));
+            assert_eq!((&runs[2].text[..], runs[2].color), ("a much longer line", GRAY));
+            assert_eq!(runs[0].rect.y, runs[1].rect.y);
+            assert!(runs[1].rect.y < runs[2].rect.y);
+
+            // both lines end at the right edge, give or take the kerning
+            // between words measured apart
+            assert!((runs[1].rect.x + runs[1].rect.w - layout.w).abs() <= 1.);
+            assert!((runs[2].rect.x + runs[2].rect.w - layout.w).abs() <= 1.);
+            assert_eq!(runs[2].rect.x, 0.);
+        });
+    }
+}
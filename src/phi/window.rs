@@ -0,0 +1,240 @@
+use ::phi::settings::Settings;
+use ::sdl2::VideoSubsystem;
+use ::sdl2::render::Renderer;
+use ::sdl2::video::FullscreenType;
+
+const SECTION: &'static str = "window";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fullscreen {
+    Windowed,
+    // changes the display's video mode to the window's size
+    Exclusive,
+    // covers the display at its current video mode
+    Desktop,
+}
+
+impl Fullscreen {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Fullscreen::Windowed => "windowed",
+            Fullscreen::Exclusive => "fullscreen",
+            Fullscreen::Desktop => "desktop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Fullscreen> {
+        match name {
+            "windowed" => Some(Fullscreen::Windowed),
+            "fullscreen" => Some(Fullscreen::Exclusive),
+            "desktop" => Some(Fullscreen::Desktop),
+            _ => None,
+        }
+    }
+
+    pub fn to_sdl(&self) -> FullscreenType {
+        match *self {
+            Fullscreen::Windowed => FullscreenType::Off,
+            Fullscreen::Exclusive => FullscreenType::True,
+            Fullscreen::Desktop => FullscreenType::Desktop,
+        }
+    }
+}
+
+// How the logical resolution is stretched over the window, always keeping its
+// aspect ratio and leaving black bars around it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling {
+    // as large as fits
+    Fit,
+    // by whole multiples for crisp pixels, unless the window is too small
+    Integer,
+}
+
+impl Scaling {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Scaling::Fit => "fit",
+            Scaling::Integer => "integer",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Scaling> {
+        match name {
+            "fit" => Some(Scaling::Fit),
+            "integer" => Some(Scaling::Integer),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RendererKind {
+    Accelerated,
+    Software,
+}
+
+impl RendererKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RendererKind::Accelerated => "accelerated",
+            RendererKind::Software => "software",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RendererKind> {
+        match name {
+            "accelerated" => Some(RendererKind::Accelerated),
+            "software" => Some(RendererKind::Software),
+            _ => None,
+        }
+    }
+}
+
+// How `spawn` opens the window, overridden by the `window` settings section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: Fullscreen,
+    pub scaling: Scaling,
+    pub vsync: bool,
+    pub renderer: RendererKind,
+    // the display the window is centered on
+    pub display: i32,
+}
+
+impl WindowConfig {
+    pub fn new(width: u32, height: u32) -> WindowConfig {
+        WindowConfig {
+            width: width,
+            height: height,
+            fullscreen: Fullscreen::Windowed,
+            scaling: Scaling::Fit,
+            vsync: false,
+            renderer: RendererKind::Accelerated,
+            display: 0,
+        }
+    }
+
+    // Values missing from the settings, or invalid, are kept as they are.
+    pub fn with_settings(mut self, settings: &Settings) -> WindowConfig {
+        let get = |key: &str| settings.get(SECTION, key).map(|value| value.trim());
+        let invalid = |key: &str, value: &str| println!("Invalid window setting {} = {}", key, value);
+
+        if let Some(value) = get("width") {
+            match value.parse() {
+                Ok(width) if width > 0 => self.width = width,
+                _ => invalid("width", value),
+            }
+        }
+
+        if let Some(value) = get("height") {
+            match value.parse() {
+                Ok(height) if height > 0 => self.height = height,
+                _ => invalid("height", value),
+            }
+        }
+
+        if let Some(value) = get("fullscreen") {
+            match Fullscreen::from_name(value) {
+                Some(fullscreen) => self.fullscreen = fullscreen,
+                None => invalid("fullscreen", value),
+            }
+        }
+
+        if let Some(value) = get("scaling") {
+            match Scaling::from_name(value) {
+                Some(scaling) => self.scaling = scaling,
+                None => invalid("scaling", value),
+            }
+        }
+
+        if let Some(value) = get("vsync") {
+            match value.parse() {
+                Ok(vsync) => self.vsync = vsync,
+                Err(_) => invalid("vsync", value),
+            }
+        }
+
+        if let Some(value) = get("renderer") {
+            match RendererKind::from_name(value) {
+                Some(renderer) => self.renderer = renderer,
+                None => invalid("renderer", value),
+            }
+        }
+
+        if let Some(value) = get("display") {
+            match value.parse() {
+                Ok(display) if display >= 0 => self.display = display,
+                _ => invalid("display", value),
+            }
+        }
+
+        self
+    }
+
+    pub fn write_settings(&self, settings: &mut Settings) {
+        settings.set(SECTION, "width", &self.width.to_string());
+        settings.set(SECTION, "height", &self.height.to_string());
+        settings.set(SECTION, "fullscreen", self.fullscreen.name());
+        settings.set(SECTION, "scaling", self.scaling.name());
+        settings.set(SECTION, "vsync", &self.vsync.to_string());
+        settings.set(SECTION, "renderer", self.renderer.name());
+        settings.set(SECTION, "display", &self.display.to_string());
+    }
+
+    // Falls back to a software renderer when the chosen one can't be created,
+    // e.g. without working graphics drivers.
+    pub fn open(&self, video: &VideoSubsystem, title: &str) -> Result<Renderer<'static>, String> {
+        match self.open_with(video, title, self.renderer) {
+            Err(ref err) if self.renderer != RendererKind::Software => {
+                println!("Falling back to a software renderer: {}", err);
+                self.open_with(video, title, RendererKind::Software)
+            },
+            result => result,
+        }
+    }
+
+    fn open_with(&self, video: &VideoSubsystem, title: &str, kind: RendererKind) -> Result<Renderer<'static>, String> {
+        let mut builder = video.window(title, self.width, self.height);
+        builder.resizable();
+
+        match video.display_bounds(self.display) {
+            Ok(bounds) => {
+                builder.position(
+                    bounds.x() + (bounds.width() as i32 - self.width as i32) / 2,
+                    bounds.y() + (bounds.height() as i32 - self.height as i32) / 2);
+            },
+            Err(err) => {
+                println!("No display {}, using the default one: {:?}", self.display, err);
+                builder.position_centered();
+            },
+        }
+
+        match self.fullscreen {
+            Fullscreen::Windowed => {},
+            Fullscreen::Exclusive => { builder.fullscreen(); },
+            Fullscreen::Desktop => { builder.fullscreen_desktop(); },
+        }
+
+        if kind == RendererKind::Accelerated {
+            builder.opengl();
+        }
+
+        let window = builder.build()
+            .map_err(|err| format!("could not open the window: {:?}", err))?;
+
+        let mut renderer = window.renderer();
+        renderer = match kind {
+            RendererKind::Accelerated => renderer.accelerated(),
+            RendererKind::Software => renderer.software(),
+        };
+        if self.vsync {
+            renderer = renderer.present_vsync();
+        }
+
+        renderer.build()
+            .map_err(|err| format!("could not create a {} renderer: {:?}", kind.name(), err))
+    }
+}
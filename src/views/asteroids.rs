@@ -0,0 +1,212 @@
+use ::phi::Phi;
+use ::phi::camera::Camera;
+use ::phi::data::{Circle, Rectangle, Shape, Vec2};
+use ::phi::gfx::{AnimatedSprite, CopySprite, DrawParams, PlayMode};
+use ::rand::{Rng, SeedableRng, XorShiftRng};
+
+const ASTEROID_PATH: &'static str = "asteroid.png";
+const ASTEROID_FRAMES: usize = 24;
+const ASTEROID_SIDE: f64 = 64.;
+// the part of the sprite's side the rock itself covers
+const ASTEROID_ROCK: f64 = 0.8;
+
+const EXPLOSION_PATH: &'static str = "explosion.png";
+const EXPLOSION_FRAMES: usize = 16;
+const EXPLOSION_SIDE: f64 = 64.;
+const EXPLOSION_FPS: f64 = 24.;
+
+fn load_animation(phi: &mut Phi, name: &str, count: usize, side: f64, fps: f64) -> Result<AnimatedSprite, String> {
+    let spritesheet = phi.texture(name)?;
+    AnimatedSprite::from_grid(&spritesheet, side, side, count, 1. / fps)
+        .ok_or(format!("{} is too small for {} frames of {}px", name, count, side))
+}
+
+fn lerp_rect(prev: Rectangle, rect: Rectangle, alpha: f64) -> Rectangle {
+    rect.with_pos(prev.pos().lerp(rect.pos(), alpha))
+}
+
+pub struct Asteroid {
+    sprite: AnimatedSprite,
+    rect: Rectangle,
+    prev_rect: Rectangle,
+    vel: Vec2,
+    // degrees, turning at `spin` degrees per second
+    angle: f64,
+    prev_angle: f64,
+    spin: f64,
+
+    health: u32,
+    max_health: u32,
+}
+
+impl Asteroid {
+    pub fn rect(&self) -> Rectangle {
+        self.rect
+    }
+
+    pub fn shape(&self) -> Shape {
+        Shape::Circle(Circle {
+            center: self.rect.center(),
+            radius: self.rect.w * ASTEROID_ROCK / 2.,
+        })
+    }
+
+    pub fn vel(&self) -> Vec2 {
+        self.vel
+    }
+
+    // Flies left, bouncing off the top and bottom of `bounds`.
+    pub fn update(&mut self, bounds: Rectangle, dt: f64) {
+        self.prev_rect = self.rect;
+        self.prev_angle = self.angle;
+        self.sprite.update(dt);
+        self.angle = (self.angle + self.spin * dt) % 360.;
+
+        self.rect = self.rect.translate(self.vel * dt);
+
+        if (self.rect.y < bounds.y && self.vel.y < 0.) ||
+           (self.rect.y + self.rect.h > bounds.y + bounds.h && self.vel.y > 0.) {
+            self.vel.y = -self.vel.y;
+        }
+    }
+
+    pub fn damage(&mut self, amount: u32) {
+        self.health = self.health.saturating_sub(amount);
+    }
+
+    pub fn destroy(&mut self) {
+        self.health = 0;
+    }
+
+    pub fn destroyed(&self) -> bool {
+        self.health == 0
+    }
+
+    // Bigger asteroids take more hits and are worth more.
+    pub fn points(&self) -> u32 {
+        self.max_health * 10
+    }
+
+    // Whether it has left `bounds` through the left edge.
+    pub fn gone(&self, bounds: Rectangle) -> bool {
+        self.rect.x + self.rect.w < bounds.x
+    }
+
+    pub fn draw(&self, phi: &mut Phi, camera: &Camera, alpha: f64) {
+        // the angle may have wrapped around since the previous update
+        let angle = if (self.angle - self.prev_angle).abs() > 180. { self.angle }
+                    else { self.prev_angle + (self.angle - self.prev_angle) * alpha };
+
+        phi.renderer.copy_sprite_world_ex(camera, &self.sprite, lerp_rect(self.prev_rect, self.rect, alpha), &DrawParams {
+            angle: angle,
+            ..DrawParams::default()
+        });
+    }
+}
+
+// Sends asteroids of random sizes and speeds in from the right edge.
+pub struct AsteroidFactory {
+    sprite: AnimatedSprite,
+    rng: XorShiftRng,
+    // seconds until the next asteroid
+    cooldown: f64,
+}
+
+impl AsteroidFactory {
+    pub fn new(phi: &mut Phi) -> Result<AsteroidFactory, String> {
+        Ok(AsteroidFactory {
+            sprite: load_animation(phi, ASTEROID_PATH, ASTEROID_FRAMES, ASTEROID_SIDE, 20.)?,
+            rng: XorShiftRng::from_seed(phi.events.next_seed()),
+            cooldown: 1.,
+        })
+    }
+
+    pub fn update(&mut self, bounds: Rectangle, dt: f64) -> Option<Asteroid> {
+        self.cooldown -= dt;
+        if self.cooldown > 0. {
+            return None;
+        }
+
+        self.cooldown = self.rng.gen_range(0.4, 1.4);
+
+        let scale = self.rng.gen_range(0.6, 1.3);
+        let side = ASTEROID_SIDE * scale;
+        let rect = Rectangle {
+            x: bounds.x + bounds.w,
+            y: bounds.y + self.rng.gen_range(0., bounds.h - side),
+            w: side,
+            h: side,
+        };
+        let health = if scale < 0.8 { 1 } else if scale < 1.1 { 2 } else { 3 };
+
+        let mut sprite = self.sprite.clone();
+        // spinning both slower and faster than the sheet's pace
+        sprite.set_frame_duration(1. / self.rng.gen_range(8., 30.));
+        sprite.set_frame(self.rng.gen_range(0, ASTEROID_FRAMES));
+        let angle = self.rng.gen_range(0., 360.);
+
+        Some(Asteroid {
+            sprite: sprite,
+            rect: rect,
+            prev_rect: rect,
+            vel: Vec2::new(-self.rng.gen_range(60., 180.), self.rng.gen_range(-40., 40.)),
+            angle: angle,
+            prev_angle: angle,
+            spin: self.rng.gen_range(-90., 90.),
+
+            health: health,
+            max_health: health,
+        })
+    }
+}
+
+pub struct Explosion {
+    sprite: AnimatedSprite,
+    rect: Rectangle,
+    prev_rect: Rectangle,
+    vel: Vec2,
+}
+
+impl Explosion {
+    pub fn update(&mut self, dt: f64) {
+        self.prev_rect = self.rect;
+        self.sprite.update(dt);
+        self.rect = self.rect.translate(self.vel * dt);
+    }
+
+    pub fn finished(&self) -> bool {
+        self.sprite.finished()
+    }
+
+    pub fn draw(&self, phi: &mut Phi, camera: &Camera, alpha: f64) {
+        phi.renderer.copy_sprite_world(camera, &self.sprite, lerp_rect(self.prev_rect, self.rect, alpha));
+    }
+}
+
+pub struct ExplosionFactory {
+    sprite: AnimatedSprite,
+}
+
+impl ExplosionFactory {
+    pub fn new(phi: &mut Phi) -> Result<ExplosionFactory, String> {
+        let mut sprite = load_animation(phi, EXPLOSION_PATH, EXPLOSION_FRAMES, EXPLOSION_SIDE, EXPLOSION_FPS)?;
+        sprite.set_mode(PlayMode::Once);
+
+        Ok(ExplosionFactory {
+            sprite: sprite,
+        })
+    }
+
+    // An explosion covering `rect` and drifting along at half of `vel`.
+    pub fn at(&self, rect: Rectangle, vel: Vec2) -> Explosion {
+        let side = rect.w.max(rect.h) * 1.5;
+        let rect = Rectangle::centered(rect.center(), Vec2::new(side, side));
+
+        Explosion {
+            sprite: self.sprite.clone(),
+            rect: rect,
+            prev_rect: rect,
+            vel: vel / 2.,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use ::phi::Phi;
+use ::phi::camera::Camera;
+use ::phi::data::{Rectangle, Shape, Vec2};
+use ::sdl2::pixels::Color;
+use ::std::f64::consts::PI;
+
+const BULLET_SPEED: f64 = 240.;
+const BULLET_W: f64 = 8.;
+const BULLET_H: f64 = 4.;
+// seconds before a bullet disappears, even if still on screen
+const BULLET_LIFETIME: f64 = 4.;
+
+const SPREAD_ANGLE: f64 = PI / 12.;
+const SINE_AMPLITUDE: f64 = 20.;
+const SINE_ANGULAR_VEL: f64 = 12.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Weapon {
+    Single,
+    Spread,
+    Sine,
+}
+
+impl Weapon {
+    pub fn next(self) -> Weapon {
+        match self {
+            Weapon::Single => Weapon::Spread,
+            Weapon::Spread => Weapon::Sine,
+            Weapon::Sine => Weapon::Single,
+        }
+    }
+
+    // seconds between two shots
+    pub fn cooldown(self) -> f64 {
+        match self {
+            Weapon::Single => 0.15,
+            Weapon::Spread => 0.35,
+            Weapon::Sine => 0.2,
+        }
+    }
+
+    // Bullets leaving the cannon at `pos`, the middle of their left edge.
+    pub fn fire(self, pos: Vec2) -> Vec<Bullet> {
+        match self {
+            Weapon::Single => vec![
+                Bullet::new(pos, Motion::Straight { vel: Vec2::new(BULLET_SPEED, 0.) }),
+            ],
+            Weapon::Spread => [-SPREAD_ANGLE, 0., SPREAD_ANGLE].iter().map(|&angle| {
+                Bullet::new(pos, Motion::Straight {
+                    vel: Vec2::from_angle(angle) * BULLET_SPEED,
+                })
+            }).collect(),
+            // two bullets in opposite phases, crossing each other
+            Weapon::Sine => [SINE_AMPLITUDE, -SINE_AMPLITUDE].iter().map(|&amplitude| {
+                Bullet::new(pos, Motion::Sine {
+                    origin_y: pos.y,
+                    amplitude: amplitude,
+                    angular_vel: SINE_ANGULAR_VEL,
+                })
+            }).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Motion {
+    Straight { vel: Vec2 },
+    Sine { origin_y: f64, amplitude: f64, angular_vel: f64 },
+}
+
+pub struct Bullet {
+    rect: Rectangle,
+    prev_rect: Rectangle,
+    age: f64,
+    motion: Motion,
+}
+
+impl Bullet {
+    fn new(pos: Vec2, motion: Motion) -> Bullet {
+        let rect = Rectangle::from_vecs(pos - Vec2::new(0., BULLET_H / 2.), Vec2::new(BULLET_W, BULLET_H));
+
+        Bullet {
+            rect: rect,
+            prev_rect: rect,
+            age: 0.,
+            motion: motion,
+        }
+    }
+
+    // Whether the bullet went through `shape` during the last update, which
+    // fast bullets could skip over between two updates.
+    pub fn hits(&self, shape: &Shape) -> bool {
+        Shape::from(self.prev_rect).sweep(self.rect.pos() - self.prev_rect.pos(), shape).is_some()
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.prev_rect = self.rect;
+        self.age += dt;
+
+        match self.motion {
+            Motion::Straight { vel } => {
+                self.rect = self.rect.translate(vel * dt);
+            },
+            Motion::Sine { origin_y, amplitude, angular_vel } => {
+                self.rect.x += BULLET_SPEED * dt;
+                self.rect.y = origin_y - BULLET_H / 2. + amplitude * (angular_vel * self.age).sin();
+            },
+        }
+    }
+
+    // Whether the bullet is young enough and at least partly inside `bounds`.
+    pub fn alive(&self, bounds: Rectangle) -> bool {
+        self.age < BULLET_LIFETIME && bounds.overlaps(self.rect)
+    }
+
+    pub fn draw(&self, phi: &mut Phi, camera: &Camera, alpha: f64) {
+        let rect = self.rect.with_pos(self.prev_rect.pos().lerp(self.rect.pos(), alpha));
+
+        phi.renderer.set_draw_color(Color::RGB(230, 230, 30));
+        phi.renderer.fill_rect(camera.to_screen(rect).to_sdl().unwrap());
+    }
+}
@@ -0,0 +1,220 @@
+use ::phi::{Phi, View, ViewAction, Action};
+use ::phi::bindings::Bindings;
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::text::{Align, TextStyle};
+use ::views::shared::{BgSet, FONT};
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::Color;
+
+const ROW_H: f64 = 36.;
+const BOX_W: f64 = 560.;
+// keys named in the hints below the box
+const KEY_COLOR: Color = Color::RGB(255, 255, 120);
+
+fn hint_style() -> TextStyle {
+    let mut style = TextStyle::new(FONT, 18, Color::RGB(180, 180, 180));
+    style.align = Align::Center;
+    style.max_width = Some(BOX_W);
+    style
+}
+
+struct Row {
+    action: Action,
+    idle_sprite: Sprite,
+    hover_sprite: Sprite,
+    keys_sprite: Sprite,
+}
+
+impl Row {
+    fn new(phi: &mut Phi, action: Action) -> Result<Row, String> {
+        let name = action.name().replace('_', " ");
+        let bindings = phi.events.bindings();
+        let mut keys: Vec<String> = bindings.keys(action).iter().map(|key| key.name()).collect();
+        keys.extend(bindings.buttons(action).iter().map(|button| format!("pad {}", button.string())));
+        let keys = if keys.is_empty() { "-".to_string() } else { keys.join(", ") };
+
+        Ok(Row {
+            action: action,
+            idle_sprite: phi.ttf_str_sprite(&name, FONT, 24, Color::RGB(220, 220, 220))?,
+            hover_sprite: phi.ttf_str_sprite(&name, FONT, 24, Color::RGB(255, 255, 120))?,
+            keys_sprite: phi.ttf_str_sprite(&keys, FONT, 24, Color::RGB(220, 220, 220))?,
+        })
+    }
+
+    fn all(phi: &mut Phi) -> Result<Vec<Row>, String> {
+        Action::all().iter().map(|&action| Row::new(phi, action)).collect()
+    }
+}
+
+pub struct ControlsView {
+    rows: Vec<Row>,
+    // the entry after the last row resets the defaults
+    selected: usize,
+    waiting: bool,
+
+    reset_idle: Sprite,
+    reset_hover: Sprite,
+    hint: Sprite,
+    prompt: Sprite,
+
+    bg_set: BgSet,
+}
+
+impl ControlsView {
+    pub fn new(phi: &mut Phi, bg_set: BgSet) -> Result<ControlsView, String> {
+        Ok(ControlsView {
+            rows: Row::all(phi)?,
+            selected: 0,
+            waiting: false,
+
+            reset_idle: phi.ttf_str_sprite("reset defaults", FONT, 24, Color::RGB(220, 220, 220))?,
+            reset_hover: phi.ttf_str_sprite("reset defaults", FONT, 24, Color::RGB(255, 255, 120))?,
+            hint: phi.text_sprite(&[
+                ("Enter", Some(KEY_COLOR)), (" to rebind, ", None), ("Escape", Some(KEY_COLOR)), (" to go back", None),
+            ], &hint_style())?,
+            prompt: phi.text_sprite(&[
+                ("Press a key or button to bind it to the action, or ", None), ("Escape", Some(KEY_COLOR)),
+                (" to keep the old one", None),
+            ], &hint_style())?,
+
+            bg_set: bg_set,
+        })
+    }
+
+    fn apply(&mut self, phi: &mut Phi, bindings: Bindings) {
+        bindings.write_settings(&mut phi.settings);
+        phi.save_settings();
+        phi.events.set_bindings(bindings);
+
+        // the bindings are applied even if the rows can't show them
+        match Row::all(phi) {
+            Ok(rows) => self.rows = rows,
+            Err(err) => println!("{}", err),
+        }
+    }
+}
+
+impl View for ControlsView {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        self.bg_set.update(dt);
+
+        if self.waiting {
+            let action = self.rows[self.selected].action;
+
+            // a new key replaces the keyboard bindings, a new button the controller ones
+            if let Some(key) = phi.events.now.key_pressed {
+                self.waiting = false;
+
+                if key != Keycode::Escape {
+                    let mut bindings = phi.events.bindings().clone();
+                    bindings.set(action, vec![key]);
+                    self.apply(phi, bindings);
+                }
+            } else if let Some(button) = phi.events.now.button_pressed {
+                self.waiting = false;
+
+                let mut bindings = phi.events.bindings().clone();
+                bindings.set_buttons(action, vec![button]);
+                self.apply(phi, bindings);
+            }
+
+            return ViewAction::None;
+        }
+
+        if phi.events.now.cancel == Some(true) {
+            return ViewAction::Pop;
+        }
+
+        if phi.events.now.confirm == Some(true) {
+            if self.selected < self.rows.len() {
+                self.waiting = true;
+            } else {
+                self.apply(phi, Bindings::new());
+            }
+        }
+
+        let entries = self.rows.len() + 1;
+
+        if phi.events.now.move_up == Some(true) {
+            self.selected = (self.selected + entries - 1) % entries;
+        }
+
+        if phi.events.now.move_down == Some(true) {
+            self.selected = (self.selected + 1) % entries;
+        }
+
+        ViewAction::None
+    }
+
+    fn draw(&self, phi: &mut Phi, alpha: f64) {
+        self.bg_set.render_bg(&mut phi.renderer, alpha);
+
+        let (win_w, win_h) = phi.output_size();
+        let box_h = (self.rows.len() + 1) as f64 * ROW_H;
+        let box_x = (win_w - BOX_W) / 2.;
+        let box_y = (win_h - box_h) / 2.;
+        let margin: f64 = 16.;
+
+        phi.renderer.set_draw_color(Color::RGB(70, 15, 70));
+        phi.renderer.fill_rect(Rectangle {
+            x: box_x - margin,
+            y: box_y - margin,
+            w: BOX_W + margin * 2.,
+            h: box_h + margin * 2.,
+        }.to_sdl().unwrap());
+
+        for (i_row, row) in self.rows.iter().enumerate() {
+            let y = box_y + ROW_H * i_row as f64;
+
+            let name =
+                if i_row == self.selected { &row.hover_sprite }
+                else { &row.idle_sprite };
+
+            let (w, h) = name.size();
+            phi.renderer.copy_sprite(name, Rectangle {
+                x: box_x,
+                y: y + (ROW_H - h) / 2.,
+                w: w,
+                h: h,
+            });
+
+            // while waiting for a key, the prompt replaces the selected binding
+            if !(self.waiting && i_row == self.selected) {
+                let (w, h) = row.keys_sprite.size();
+                phi.renderer.copy_sprite(&row.keys_sprite, Rectangle {
+                    x: box_x + BOX_W - w,
+                    y: y + (ROW_H - h) / 2.,
+                    w: w,
+                    h: h,
+                });
+            }
+        }
+
+        let reset =
+            if self.selected == self.rows.len() { &self.reset_hover }
+            else { &self.reset_idle };
+
+        let (w, h) = reset.size();
+        phi.renderer.copy_sprite(reset, Rectangle {
+            x: box_x,
+            y: box_y + ROW_H * self.rows.len() as f64 + (ROW_H - h) / 2.,
+            w: w,
+            h: h,
+        });
+
+        let hint =
+            if self.waiting { &self.prompt }
+            else { &self.hint };
+
+        let (w, h) = hint.size();
+        phi.renderer.copy_sprite(hint, Rectangle {
+            x: (win_w - w) / 2.,
+            y: box_y + box_h + margin * 2.,
+            w: w,
+            h: h,
+        });
+
+        self.bg_set.render_fg(&mut phi.renderer, alpha);
+    }
+}
@@ -1,14 +1,33 @@
 use ::phi::{Phi, View, ViewAction};
-use ::phi::data::Rectangle;
-use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::audio::Sound;
+use ::phi::camera::Camera;
+use ::phi::data::{Polygon, Rectangle, Shape, Vec2};
+use ::phi::gfx::{Sprite, CopySprite, DrawParams};
 use ::sdl2::pixels::Color;
-use ::sdl2::render::Renderer;
-use ::views::shared::BgSet;
+use ::views::asteroids::{Asteroid, AsteroidFactory, Explosion, ExplosionFactory};
+use ::views::bullets::{Bullet, Weapon};
+use ::views::name_entry::{best_score, NameEntryView};
+use ::views::shared::{BgSet, PIXEL_FONT};
 
 const PLAYER_SPEED: f64 = 180.;
-const SHIP_W: f64 = 43.;
-const SHIP_H: f64 = 39.;
 const DEBUG: bool = false;
+const SHOT_SOUND: &'static str = "shot.wav";
+const EXPLOSION_SOUND: &'static str = "explosion.wav";
+const MUSIC: &'static str = "game.wav";
+// shots are frequent enough to cut each other off rather than take up every
+// channel
+const SHOT_CHANNEL: usize = 0;
+
+const PLAYER_HEALTH: u32 = 3;
+// seconds the ship can't be hit again after taking damage
+const INVULNERABLE_TIME: f64 = 1.5;
+// seconds between the ship blowing up and the game ending
+const GAME_OVER_DELAY: f64 = 2.;
+
+// how much taller the world is than the screen, which follows the ship
+const WORLD_HEIGHT: f64 = 1.5;
+// the part of the world's width the ship may fly in
+const PLAYER_AREA_WIDTH: f64 = 0.7;
 
 #[derive(Clone, Copy)]
 enum ShipFrame {
@@ -23,96 +42,128 @@ enum ShipFrame {
     DownSlow = 8,
 }
 
+// region names in `spaceship.json`, in the order of `ShipFrame`
+const SHIP_FRAMES: [&'static str; 9] = [
+    "up_norm", "up_fast", "up_slow",
+    "mid_norm", "mid_fast", "mid_slow",
+    "down_norm", "down_fast", "down_slow",
+];
+
 struct Ship {
     rect: Rectangle,
+    prev_rect: Rectangle,
     sprites: Vec<Sprite>,
+    pivots: Vec<Vec2>,
     current: ShipFrame,
+
+    weapon: Weapon,
+    // seconds left before the next shot is allowed
+    cooldown: f64,
+
+    health: u32,
+    invulnerable: f64,
 }
 
 pub struct ShipView {
     player: Ship,
+    bullets: Vec<Bullet>,
+    asteroids: Vec<Asteroid>,
+    asteroid_factory: AsteroidFactory,
+    explosions: Vec<Explosion>,
+    explosion_factory: ExplosionFactory,
+
+    score: u32,
+    // seconds left until the game ends, once the ship has been destroyed
+    game_over: Option<f64>,
+
+    world: Rectangle,
+    camera: Camera,
+
+    shot_sound: Sound,
+    explosion_sound: Sound,
 
     bg_set: BgSet,
 }
 
 impl ShipView {
-    pub fn new(phi: &mut Phi) -> ShipView {
-        let bg_set = BgSet::new(&mut phi.renderer, "assets/starBG.png", "assets/starMG.png", "assets/starFG.png");
-        ShipView::with_backgrounds(phi, bg_set)
-    }
+    pub fn with_backgrounds(phi: &mut Phi, bg_set: BgSet) -> Result<ShipView, String> {
+        let atlas = phi.atlas("spaceship.json")?;
+        let mut sprites = Vec::with_capacity(SHIP_FRAMES.len());
+        let mut pivots = Vec::with_capacity(SHIP_FRAMES.len());
 
-    pub fn with_backgrounds(phi: &mut Phi, bg_set: BgSet) -> ShipView {
-        let spritesheet = Sprite::load(&mut phi.renderer, "assets/spaceship.png").unwrap();
-        let mut sprites = Vec::with_capacity(9);
-
-        for y in 0..3 {
-            for x in 0..3 {
-                sprites.push(spritesheet.region(Rectangle {
-                    x: SHIP_W * x as f64,
-                    y: SHIP_H * y as f64,
-                    w: SHIP_W,
-                    h: SHIP_H,
-                }).unwrap());
-            }
+        for name in SHIP_FRAMES.iter() {
+            let missing = || format!("no region {} in spaceship.json", name);
+            sprites.push(atlas.sprite(name).ok_or_else(&missing)?);
+            pivots.push(atlas.pivot(name).map(Vec2::from).ok_or_else(&missing)?);
         }
 
+        let (win_w, win_h) = phi.output_size();
+        let world = Rectangle {
+            x: 0.,
+            y: 0.,
+            w: win_w,
+            h: win_h * WORLD_HEIGHT,
+        };
 
-        ShipView {
+        // the resting frame gives the size the ship collides with
+        let (ship_w, ship_h) = sprites[ShipFrame::MidNorm as usize].size();
+        let rect = Rectangle {
+            x: 64.,
+            y: (world.h - ship_h) / 2.,
+            w: ship_w,
+            h: ship_h,
+        };
+
+        let mut camera = Camera::new(win_w, win_h);
+        camera.set_bounds(Some(world));
+        camera.look_at(world.w / 2., rect.y + rect.h / 2.);
+
+        Ok(ShipView {
             player: Ship {
-                rect: Rectangle {
-                    x: 64.,
-                    y: 64.,
-                    w: SHIP_W, // width as f64 / 3.,
-                    h: SHIP_H, // height as f64 / 3.,
-                },
+                rect: rect,
+                prev_rect: rect,
                 sprites: sprites,
+                pivots: pivots,
                 current: ShipFrame::MidNorm,
+
+                weapon: Weapon::Single,
+                cooldown: 0.,
+
+                health: PLAYER_HEALTH,
+                invulnerable: 0.,
             },
-            
-            bg_set: bg_set,
-        }
-    }
-}
+            bullets: vec![],
+            asteroids: vec![],
+            asteroid_factory: AsteroidFactory::new(phi)?,
+            explosions: vec![],
+            explosion_factory: ExplosionFactory::new(phi)?,
 
-impl View for ShipView {
-    fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
-        if phi.events.now.quit {
-            return ViewAction::Quit;
-        }
-        
-        if phi.events.now.key_escape == Some(true) {
-            return ViewAction::ChangeView(Box::new(
-                    ::views::main_menu::MainMenuView::with_backgrounds(phi, self.bg_set.clone())
-            ));
-        }
+            score: 0,
+            game_over: None,
 
-        let diagonal = (phi.events.key_up ^ phi.events.key_down) &&
-                       (phi.events.key_left ^ phi.events.key_right);
+            world: world,
+            camera: camera,
 
-        let moved =
-            if diagonal { 1./2f64.sqrt() }
-            else { 1. } * PLAYER_SPEED * elapsed;
+            shot_sound: phi.sound(SHOT_SOUND)?,
+            explosion_sound: phi.sound(EXPLOSION_SOUND)?,
 
-        let dx = match (phi.events.key_left, phi.events.key_right) {
-            (true, true) | (false, false) => 0.,
-            (true, false) => -moved,
-            (false, true) => moved,
-        };
+            bg_set: bg_set,
+        })
+    }
+}
 
-        let dy = match (phi.events.key_up, phi.events.key_down) {
-            (true, true) | (false, false) => 0.,
-            (true, false) => -moved,
-            (false, true) => moved,
-        };
+impl ShipView {
+    fn update_player(&mut self, phi: &mut Phi, dt: f64) {
+        // analog sticks give any direction and any speed up to the maximum
+        let delta = phi.events.movement() * PLAYER_SPEED * dt;
+        let (dx, dy) = (delta.x, delta.y);
 
-        self.player.rect.x += dx;
-        self.player.rect.y += dy;
+        self.player.prev_rect = self.player.rect;
+        self.player.rect = self.player.rect.translate(delta);
 
         let movable_region = Rectangle {
-            x: 0.,
-            y: 0.,
-            w: phi.output_size().0 * 0.7,
-            h: phi.output_size().1,
+            w: self.world.w * PLAYER_AREA_WIDTH,
+            ..self.world
         };
 
         self.player.rect = self.player.rect.move_inside(movable_region).unwrap();
@@ -129,21 +180,215 @@ impl View for ShipView {
             else if dx < 0. && dy > 0. { ShipFrame::DownSlow }
             else { unreachable!() };
 
-        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
-        phi.renderer.clear();
+        self.player.invulnerable = (self.player.invulnerable - dt).max(0.);
+
+        if phi.events.now.switch_weapon == Some(true) {
+            self.player.weapon = self.player.weapon.next();
+        }
+
+        self.player.cooldown = (self.player.cooldown - dt).max(0.);
+        if phi.events.fire && self.player.cooldown == 0. {
+            let rect = self.player.rect;
+            self.bullets.extend(self.player.weapon.fire(Vec2::new(rect.x + rect.w - 10., rect.center().y)));
+            self.player.cooldown = self.player.weapon.cooldown();
+            phi.audio.play_sound_on(SHOT_CHANNEL, &self.shot_sound);
+        }
+    }
+
+    fn explode(&mut self, phi: &mut Phi, rect: Rectangle, vel: Vec2) {
+        self.camera.shake(6., 0.3);
+        self.explosions.push(self.explosion_factory.at(rect, vel));
+        phi.audio.play_sound(&self.explosion_sound);
+    }
+}
+
+// The ship's hull within `rect`, pointing right.
+fn ship_shape(rect: Rectangle) -> Shape {
+    let point = |x: f64, y: f64| Vec2::new(rect.x + rect.w * x, rect.y + rect.h * y);
+
+    Shape::Polygon(Polygon::new(vec![
+        point(0., 0.2),
+        point(0.3, 0.),
+        point(1., 0.5),
+        point(0.3, 1.),
+        point(0., 0.8),
+    ]).unwrap())
+}
+
+impl View for ShipView {
+    fn resume(&mut self, phi: &mut Phi) {
+        phi.audio.reserve_channels(SHOT_CHANNEL + 1);
+        phi.audio.set_channel_volume(SHOT_CHANNEL, 0.7);
+    }
+
+    fn pause(&mut self, phi: &mut Phi) {
+        phi.audio.stop_channel(SHOT_CHANNEL);
+        phi.audio.reserve_channels(0);
+    }
+
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if let Some(left) = self.game_over {
+            if left <= 0. {
+                if self.score <= best_score(&phi.settings) {
+                    return ViewAction::Pop;
+                }
+
+                return match NameEntryView::new(phi, self.score) {
+                    Ok(view) => ViewAction::Replace(Box::new(view)),
+                    Err(err) => {
+                        println!("Could not ask for a name: {}", err);
+                        ViewAction::Pop
+                    },
+                };
+            }
 
-        self.bg_set.render_bg(&mut phi.renderer, elapsed);
+            self.game_over = Some(left - dt);
+        } else {
+            if phi.events.now.pause == Some(true) {
+                match ::views::pause::PauseView::new(phi) {
+                    Ok(view) => return ViewAction::Push(Box::new(view)),
+                    Err(err) => println!("Could not pause: {}", err),
+                }
+            }
+
+            self.update_player(phi, dt);
+        }
+
+        let (win_w, win_h) = phi.output_size();
+        let player_rect = self.player.rect;
+        self.camera.set_viewport(win_w, win_h);
+        self.camera.follow(self.world.w / 2., player_rect.y + player_rect.h / 2.);
+        self.camera.update(dt);
+
+        let world = self.world;
+
+        for bullet in &mut self.bullets {
+            bullet.update(dt);
+        }
+        self.bullets.retain(|bullet| bullet.alive(world));
+
+        if let Some(asteroid) = self.asteroid_factory.update(world, dt) {
+            self.asteroids.push(asteroid);
+        }
+
+        for asteroid in &mut self.asteroids {
+            asteroid.update(world, dt);
+        }
+
+        // every bullet hits at most one asteroid
+        let mut bullets = Vec::with_capacity(self.bullets.len());
+        for bullet in self.bullets.drain(..) {
+            match self.asteroids.iter_mut().find(|asteroid| bullet.hits(&asteroid.shape())) {
+                Some(asteroid) => asteroid.damage(1),
+                None => bullets.push(bullet),
+            }
+        }
+        self.bullets = bullets;
+
+        for asteroid in self.asteroids.iter().filter(|asteroid| asteroid.destroyed()) {
+            self.score += asteroid.points();
+            self.explosions.push(self.explosion_factory.at(asteroid.rect(), asteroid.vel()));
+            phi.audio.play_sound(&self.explosion_sound);
+        }
+
+        if self.game_over.is_none() && self.player.invulnerable == 0. {
+            let player_rect = self.player.rect;
+            let player_shape = ship_shape(player_rect);
+            let crashed = self.asteroids.iter_mut()
+                .find(|asteroid| player_shape.overlaps(&asteroid.shape()))
+                .map(|asteroid| {
+                    asteroid.destroy();
+                    (asteroid.rect(), asteroid.vel())
+                });
+
+            if let Some((rect, vel)) = crashed {
+                self.explode(phi, rect, vel);
+                self.player.health -= 1;
+                self.player.invulnerable = INVULNERABLE_TIME;
+
+                if self.player.health == 0 {
+                    self.explode(phi, player_rect, Vec2::default());
+                    self.camera.shake(14., 0.8);
+                    self.game_over = Some(GAME_OVER_DELAY);
+                }
+            }
+        }
+
+        self.asteroids.retain(|asteroid| !asteroid.destroyed() && !asteroid.gone(world));
+
+        for explosion in &mut self.explosions {
+            explosion.update(dt);
+        }
+        self.explosions.retain(|explosion| !explosion.finished());
+
+        self.bg_set.update(dt);
+
+        ViewAction::None
+    }
+
+    fn draw(&self, phi: &mut Phi, alpha: f64) {
+        let player_rect = self.player.rect.with_pos(self.player.prev_rect.pos().lerp(self.player.rect.pos(), alpha));
+        let camera = self.camera.interpolated(alpha);
+
+        self.bg_set.render_bg(&mut phi.renderer, alpha);
         
         if DEBUG {
             phi.renderer.set_draw_color(Color::RGB(20, 240, 180));
-            phi.renderer.fill_rect(self.player.rect.to_sdl().unwrap());
+            phi.renderer.fill_rect(camera.to_screen(player_rect).to_sdl().unwrap());
         }
 
-        phi.renderer.copy_sprite(&self.player.sprites[self.player.current as usize], self.player.rect);
+        for asteroid in &self.asteroids {
+            asteroid.draw(phi, &camera, alpha);
+        }
 
-        self.bg_set.render_fg(&mut phi.renderer, elapsed);
+        if self.game_over.is_none() {
+            // frames are placed by their pivot, at the center of the ship
+            let sprite = &self.player.sprites[self.player.current as usize];
+            let pivot = self.player.pivots[self.player.current as usize];
+            let size = Vec2::from(sprite.size());
 
-        ViewAction::None
+            // the ship flashes red while invulnerable
+            let flash = (self.player.invulnerable * 10.) as u32 % 2 == 1;
+            let params =
+                if flash { DrawParams { tint: Color::RGB(255, 80, 80), alpha: 160, ..DrawParams::default() } }
+                else { DrawParams::default() };
+
+            phi.renderer.copy_sprite_world_ex(&camera, sprite, Rectangle::from_vecs(player_rect.center() - pivot, size), &params);
+        }
+
+        for bullet in &self.bullets {
+            bullet.draw(phi, &camera, alpha);
+        }
+
+        for explosion in &self.explosions {
+            explosion.draw(phi, &camera, alpha);
+        }
+
+        self.bg_set.render_fg(&mut phi.renderer, alpha);
+
+        if let Ok(score) = phi.str_sprite(&format!("Score: {}", self.score), PIXEL_FONT, 24, Color::RGB(255, 255, 255)) {
+            let (w, h) = score.size();
+            phi.renderer.copy_sprite(&score, Rectangle {
+                x: 16.,
+                y: 12.,
+                w: w,
+                h: h,
+            });
+        }
+
+        phi.renderer.set_draw_color(Color::RGB(220, 40, 40));
+        for i_health in 0..self.player.health {
+            phi.renderer.fill_rect(Rectangle {
+                x: phi.output_size().0 - 32. - 24. * i_health as f64,
+                y: 16.,
+                w: 16.,
+                h: 16.,
+            }.to_sdl().unwrap());
+        }
+    }
+
+    fn music(&self) -> Option<&'static str> {
+        Some(MUSIC)
     }
 }
 
@@ -1,22 +1,19 @@
 use ::phi::{Phi, View, ViewAction};
-use ::phi::data::Rectangle;
-use ::phi::gfx::{Sprite, CopySprite};
-use ::views::shared::BgSet;
-use ::sdl2::pixels::Color;
+use ::views::shared::{menu_row_at, BgSet, Label, draw_menu};
+
+const MUSIC: &'static str = "menu.wav";
 
 struct Action {
     func: Box<Fn(&mut Phi, BgSet) -> ViewAction>,
-    idle_sprite: Sprite,
-    hover_sprite: Sprite,
+    label: Label,
 }
 
 impl Action {
-    fn new(phi: &mut Phi, label: &'static str, func: Box<Fn(&mut Phi, BgSet) -> ViewAction>) -> Action {
-        Action {
+    fn new(phi: &mut Phi, label: &'static str, func: Box<Fn(&mut Phi, BgSet) -> ViewAction>) -> Result<Action, String> {
+        Ok(Action {
             func: func,
-            idle_sprite: phi.ttf_str_sprite(label, "assets/belligerent.ttf", 32, Color::RGB(220, 220, 220)).unwrap(),
-            hover_sprite: phi.ttf_str_sprite(label, "assets/belligerent.ttf", 38, Color::RGB(255, 255, 255)).unwrap(),
-        }
+            label: Label::new(phi, label)?,
+        })
     }
 }
 
@@ -28,104 +25,205 @@ pub struct MainMenuView {
 }
 
 impl MainMenuView {
-    pub fn new(phi: &mut Phi) -> MainMenuView {
-        let bg_set = BgSet::new(&mut phi.renderer, "assets/starBG.png", "assets/starMG.png", "assets/starFG.png");
+    pub fn new(phi: &mut Phi) -> Result<MainMenuView, String> {
+        let bg_set = BgSet::stars(phi)?;
         MainMenuView::with_backgrounds(phi, bg_set)
     }
 
-    pub fn with_backgrounds(phi: &mut Phi, bg_set: BgSet) -> MainMenuView {
-        MainMenuView {
+    pub fn with_backgrounds(phi: &mut Phi, bg_set: BgSet) -> Result<MainMenuView, String> {
+        Ok(MainMenuView {
              actions: vec![
                 Action::new(phi, "New game", Box::new(|phi, bg| {
-                    ViewAction::ChangeView(Box::new(::views::game::ShipView::with_backgrounds(phi, bg)))
-                })),
+                    match ::views::game::ShipView::with_backgrounds(phi, bg) {
+                        Ok(view) => ViewAction::Push(Box::new(view)),
+                        Err(err) => {
+                            println!("Could not start the game: {}", err);
+                            ViewAction::None
+                        },
+                    }
+                }))?,
+                Action::new(phi, "Controls", Box::new(|phi, bg| {
+                    match ::views::controls::ControlsView::new(phi, bg) {
+                        Ok(view) => ViewAction::Push(Box::new(view)),
+                        Err(err) => {
+                            println!("Could not show the controls: {}", err);
+                            ViewAction::None
+                        },
+                    }
+                }))?,
                 Action::new(phi, "Quit", Box::new(|_, _| {
                     ViewAction::Quit
-                }))
+                }))?,
             ],
             selected: 0,
 
             bg_set: bg_set,
-        }
+        })
     }
 }
 
 impl View for MainMenuView {
-    fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
-        if phi.events.now.quit || phi.events.now.key_escape == Some(true) {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.cancel == Some(true) {
             return ViewAction::Quit;
         }
 
-        if phi.events.now.key_space == Some(true) || phi.events.now.key_return == Some(true) {
+        if phi.events.now.mouse_moved || phi.events.now.mouse_left == Some(true) {
+            if let Some(i_action) = menu_row_at(phi, self.actions.len(), phi.events.mouse) {
+                self.selected = i_action as i8;
+
+                if phi.events.now.mouse_left == Some(true) {
+                    return (self.actions[self.selected as usize].func)(phi, self.bg_set.clone());
+                }
+            }
+        }
+
+        if phi.events.now.confirm == Some(true) {
             return (self.actions[self.selected as usize].func)(phi, self.bg_set.clone());
         }
 
-        if phi.events.now.key_up == Some(true) {
+        if phi.events.now.move_up == Some(true) {
             self.selected -= 1;
             if self.selected < 0 {
                 self.selected = self.actions.len() as i8 - 1;
             }
         }
 
-        if phi.events.now.key_down == Some(true) {
+        if phi.events.now.move_down == Some(true) {
             self.selected += 1;
             if self.selected >= self.actions.len() as i8 {
                 self.selected = 0;
             }
         }
 
-        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
-        phi.renderer.clear();
-
-        self.bg_set.render_bg(&mut phi.renderer, elapsed);
-
-        let (win_w, win_h) = phi.output_size();
-        let label_h: f64 = 50.;
-        let border_width: f64 = 3.;
-        let box_w: f64 = 360.;
-        let box_h: f64 = self.actions.len() as f64 * label_h;
-        let margin_h: f64 = 10.;
-
-        // border
-        phi.renderer.set_draw_color(Color::RGB(70, 15, 70));
-        phi.renderer.fill_rect(Rectangle {
-            x: (win_w - box_w) / 2. - border_width,
-            y: (win_h - box_h) / 2. - border_width - margin_h,
-            w: box_w + border_width * 2.,
-            h: box_h + border_width * 2. + margin_h * 2.,
-        }.to_sdl().unwrap());
-
-        // menu box
-        phi.renderer.set_draw_color(Color::RGB(140, 30, 140));
-        phi.renderer.fill_rect(Rectangle {
-            x: (win_w - box_w) / 2.,
-            y: (win_h - box_h) / 2. - margin_h,
-            w: box_w,
-            h: box_h + margin_h * 2.,
-        }.to_sdl().unwrap());
-
-        for (i_action, action) in self.actions.iter().enumerate() {
-            if i_action == self.selected as usize {
-                let (w, h) = action.hover_sprite.size();
-                phi.renderer.copy_sprite(&action.hover_sprite, Rectangle {
-                    x: (win_w - w)/2.,
-                    y: (win_h - box_h + label_h - h) / 2. + label_h * i_action as f64,
-                    w: w,
-                    h: h,
-                });
-            } else {
-                let (w, h) = action.idle_sprite.size();
-                phi.renderer.copy_sprite(&action.idle_sprite, Rectangle {
-                    x: (win_w - w)/2.,
-                    y: (win_h - box_h + label_h - h) / 2. + label_h * i_action as f64,
-                    w: w,
-                    h: h,
-                });
+        self.bg_set.update(dt);
+
+        ViewAction::None
+    }
+
+    fn draw(&self, phi: &mut Phi, alpha: f64) {
+        self.bg_set.render_bg(&mut phi.renderer, alpha);
+
+        let labels: Vec<&Label> = self.actions.iter().map(|action| &action.label).collect();
+        draw_menu(phi, &labels, self.selected as usize);
+
+        self.bg_set.render_fg(&mut phi.renderer, alpha);
+    }
+
+    fn music(&self) -> Option<&'static str> {
+        Some(MUSIC)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::phi::headless::{Headless, lock_sdl};
+    use ::sdl2::event::Event;
+    use ::sdl2::keyboard::{Keycode, NOMOD};
+
+    fn menu() -> Headless {
+        Headless::new(800, 600, 60, |phi| Box::new(MainMenuView::new(phi).unwrap()))
+    }
+
+    // Holds the key for one tick and releases it on the next, returns
+    // whether the views are still running after both.
+    fn press(headless: &mut Headless, key: Keycode) -> bool {
+        let down = Event::KeyDown {
+            timestamp: 0, window_id: 0, keycode: Some(key), scancode: None, keymod: NOMOD, repeat: false,
+        };
+        let up = Event::KeyUp {
+            timestamp: 0, window_id: 0, keycode: Some(key), scancode: None, keymod: NOMOD, repeat: false,
+        };
+
+        headless.step(vec![down]) && headless.step(vec![up])
+    }
+
+    #[test]
+    fn draws_the_menu() {
+        let _lock = lock_sdl();
+        let mut headless = menu();
+        assert!(headless.step(Vec::new()));
+
+        // most of the menu box shows through between the labels
+        let mut box_pixels = 0;
+        for y in 0..600 {
+            for x in 0..800 {
+                if headless.pixel(x, y).rgb() == (140, 30, 140) {
+                    box_pixels += 1;
+                }
             }
         }
+        assert!(box_pixels > 10000, "only {} pixels of the menu box", box_pixels);
+    }
 
-        self.bg_set.render_fg(&mut phi.renderer, elapsed);
+    #[test]
+    fn scripted_keys_reach_the_events() {
+        let _lock = lock_sdl();
+        let mut headless = menu();
+
+        let down = Event::KeyDown {
+            timestamp: 0, window_id: 0, keycode: Some(Keycode::Down), scancode: None, keymod: NOMOD, repeat: false,
+        };
+        assert!(headless.step(vec![down]));
+        assert_eq!(headless.context().events.now.move_down, Some(true));
+        assert!(headless.context().events.move_down);
+    }
 
-        ViewAction::None
+    #[test]
+    fn controls_open_and_close() {
+        let _lock = lock_sdl();
+        let mut headless = menu();
+
+        assert!(press(&mut headless, Keycode::Down));
+        assert!(press(&mut headless, Keycode::Return));
+        assert_eq!(headless.views().len(), 2);
+
+        assert!(press(&mut headless, Keycode::Escape));
+        assert_eq!(headless.views().len(), 1);
+    }
+
+    #[test]
+    fn new_game_starts_the_game() {
+        let _lock = lock_sdl();
+        let mut headless = menu();
+
+        assert!(press(&mut headless, Keycode::Return));
+        assert_eq!(headless.views().len(), 2);
+    }
+
+    #[test]
+    fn pause_menu_goes_back_to_the_main_menu() {
+        let _lock = lock_sdl();
+        let mut headless = menu();
+
+        assert!(press(&mut headless, Keycode::Return));
+        assert!(press(&mut headless, Keycode::Escape));
+        assert_eq!(headless.views().len(), 3);
+
+        assert!(press(&mut headless, Keycode::Down));
+        assert!(press(&mut headless, Keycode::Return));
+        assert_eq!(headless.views().len(), 1);
+    }
+
+    #[test]
+    fn quit_empties_the_stack() {
+        let _lock = lock_sdl();
+        let mut headless = menu();
+
+        assert!(press(&mut headless, Keycode::Down));
+        assert!(press(&mut headless, Keycode::Down));
+        assert!(!press(&mut headless, Keycode::Return));
+        assert!(headless.views().is_empty());
+    }
+
+    #[test]
+    fn menu_wraps_around() {
+        let _lock = lock_sdl();
+        let mut headless = menu();
+
+        // up from the first entry selects "Quit"
+        assert!(press(&mut headless, Keycode::Up));
+        assert!(!press(&mut headless, Keycode::Return));
     }
 }
@@ -1,3 +1,8 @@
 pub mod shared;
+pub mod asteroids;
+pub mod bullets;
+pub mod controls;
 pub mod game;
 pub mod main_menu;
+pub mod name_entry;
+pub mod pause;
@@ -0,0 +1,155 @@
+use ::phi::{Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::replay::escape_chars;
+use ::phi::settings::Settings;
+use ::phi::text::{Align, TextLayout, TextStyle};
+use ::views::shared::{FONT, TextField};
+use ::sdl2::pixels::Color;
+
+const MAX_NAME_CHARS: usize = 12;
+
+// Asks for the player's name to put a new high score under.
+pub struct NameEntryView {
+    score: u32,
+    field: TextField,
+    title: Sprite,
+    hint: TextLayout,
+    hint_style: TextStyle,
+}
+
+impl NameEntryView {
+    pub fn new(phi: &mut Phi, score: u32) -> Result<NameEntryView, String> {
+        let key_color = Color::RGB(255, 255, 120);
+        let mut hint_style = TextStyle::new(FONT, 20, Color::RGB(180, 180, 180));
+        hint_style.align = Align::Center;
+
+        Ok(NameEntryView {
+            score: score,
+            field: TextField::new(MAX_NAME_CHARS),
+            title: phi.ttf_str_sprite(&format!("New high score: {}", score), FONT, 38, Color::RGB(255, 255, 255))?,
+            hint: phi.layout_text(&[
+                ("Type your name and press ", None), ("Enter", Some(key_color)),
+                ("\n", None), ("Escape", Some(key_color)), (" to skip", None),
+            ], &hint_style)?,
+            hint_style: hint_style,
+        })
+    }
+}
+
+// The best of the saved scores, 0 if there are none.
+pub fn best_score(settings: &Settings) -> u32 {
+    settings.entries("highscores").iter()
+        .filter_map(|&(_, value)| value.parse().ok())
+        .max()
+        .unwrap_or(0)
+}
+
+// Keeps the best score of every name in the `highscores` settings section.
+pub fn save_high_score(phi: &mut Phi, name: &str, score: u32) {
+    let key = score_key(name);
+    let best = phi.settings.get("highscores", &key)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+
+    if score > best {
+        phi.settings.set("highscores", &key, &score.to_string());
+        phi.save_settings();
+    }
+}
+
+// Names are escaped to be used as keys, which can't hold `=` nor start like
+// a comment or a section header. `replay::unescape` gives the name back.
+fn score_key(name: &str) -> String {
+    escape_chars(name, "=#[]")
+}
+
+impl View for NameEntryView {
+    fn resume(&mut self, phi: &mut Phi) {
+        phi.events.start_text_input();
+    }
+
+    fn pause(&mut self, phi: &mut Phi) {
+        phi.events.stop_text_input();
+    }
+
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.cancel == Some(true) {
+            return ViewAction::Pop;
+        }
+
+        if self.field.update(phi, dt) {
+            let name = self.field.text().trim().to_string();
+
+            if !name.is_empty() {
+                save_high_score(phi, &name, self.score);
+                return ViewAction::Pop;
+            }
+        }
+
+        ViewAction::None
+    }
+
+    fn draw(&self, phi: &mut Phi, _: f64) {
+        let (win_w, win_h) = phi.output_size();
+        let field_w: f64 = 360.;
+        let field_h: f64 = 48.;
+
+        let (w, h) = self.title.size();
+        phi.renderer.copy_sprite(&self.title, Rectangle {
+            x: (win_w - w) / 2.,
+            y: win_h / 2. - field_h - h,
+            w: w,
+            h: h,
+        });
+
+        self.field.draw(phi, Rectangle {
+            x: (win_w - field_w) / 2.,
+            y: (win_h - field_h) / 2.,
+            w: field_w,
+            h: field_h,
+        });
+
+        let x = (win_w - self.hint.w) / 2.;
+        let y = (win_h + field_h) / 2. + 16.;
+        if let Err(err) = phi.draw_text(&self.hint, &self.hint_style, x, y) {
+            println!("{}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::phi::replay::unescape;
+
+    #[test]
+    fn any_name_survives_the_settings_file() {
+        let names = ["a=b", "#me", "[boss]", "; x", "50% off", "two  words", "ünï"];
+        let mut settings = Settings::new();
+
+        for (i_name, name) in names.iter().enumerate() {
+            settings.set("highscores", &score_key(name), &i_name.to_string());
+        }
+
+        let loaded = Settings::parse(&settings.to_string()).unwrap();
+
+        for (i_name, name) in names.iter().enumerate() {
+            let key = score_key(name);
+            assert_eq!(loaded.get("highscores", &key), Some(&i_name.to_string()[..]));
+            assert_eq!(unescape(&key), *name);
+        }
+    }
+
+    #[test]
+    fn best_score_skips_broken_entries() {
+        let mut settings = Settings::new();
+        assert_eq!(best_score(&settings), 0);
+
+        settings.set("highscores", "ann", "120");
+        settings.set("highscores", "bob", "lots");
+        settings.set("highscores", "cid", "80");
+        settings.set("window", "width", "1024");
+        assert_eq!(best_score(&settings), 120);
+    }
+}
@@ -0,0 +1,100 @@
+use ::phi::{Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::views::shared::{menu_row_at, Label, draw_menu};
+use ::sdl2::pixels::Color;
+use ::sdl2::render::BlendMode;
+
+struct Action {
+    func: Box<Fn(&mut Phi) -> ViewAction>,
+    label: Label,
+}
+
+impl Action {
+    fn new(phi: &mut Phi, label: &'static str, func: Box<Fn(&mut Phi) -> ViewAction>) -> Result<Action, String> {
+        Ok(Action {
+            func: func,
+            label: Label::new(phi, label)?,
+        })
+    }
+}
+
+pub struct PauseView {
+    actions: Vec<Action>,
+    selected: i8,
+}
+
+impl PauseView {
+    pub fn new(phi: &mut Phi) -> Result<PauseView, String> {
+        Ok(PauseView {
+            actions: vec![
+                Action::new(phi, "Resume", Box::new(|_| {
+                    ViewAction::Pop
+                }))?,
+                Action::new(phi, "Main menu", Box::new(|_| {
+                    ViewAction::PopToRoot
+                }))?,
+            ],
+            selected: 0,
+        })
+    }
+}
+
+impl View for PauseView {
+    fn update(&mut self, phi: &mut Phi, _: f64) -> ViewAction {
+        if phi.events.now.cancel == Some(true) || phi.events.now.pause == Some(true) {
+            return ViewAction::Pop;
+        }
+
+        if phi.events.now.mouse_moved || phi.events.now.mouse_left == Some(true) {
+            if let Some(i_action) = menu_row_at(phi, self.actions.len(), phi.events.mouse) {
+                self.selected = i_action as i8;
+
+                if phi.events.now.mouse_left == Some(true) {
+                    return (self.actions[self.selected as usize].func)(phi);
+                }
+            }
+        }
+
+        if phi.events.now.confirm == Some(true) {
+            return (self.actions[self.selected as usize].func)(phi);
+        }
+
+        if phi.events.now.move_up == Some(true) {
+            self.selected -= 1;
+            if self.selected < 0 {
+                self.selected = self.actions.len() as i8 - 1;
+            }
+        }
+
+        if phi.events.now.move_down == Some(true) {
+            self.selected += 1;
+            if self.selected >= self.actions.len() as i8 {
+                self.selected = 0;
+            }
+        }
+
+        ViewAction::None
+    }
+
+    fn draw(&self, phi: &mut Phi, _: f64) {
+        let (win_w, win_h) = phi.output_size();
+
+        // dim the game below
+        phi.renderer.set_blend_mode(BlendMode::Blend);
+        phi.renderer.set_draw_color(Color::RGBA(0, 0, 0, 128));
+        phi.renderer.fill_rect(Rectangle {
+            x: 0.,
+            y: 0.,
+            w: win_w,
+            h: win_h,
+        }.to_sdl().unwrap());
+        phi.renderer.set_blend_mode(BlendMode::None);
+
+        let labels: Vec<&Label> = self.actions.iter().map(|action| &action.label).collect();
+        draw_menu(phi, &labels, self.selected as usize);
+    }
+
+    fn draws_below(&self) -> bool {
+        true
+    }
+}
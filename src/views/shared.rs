@@ -1,7 +1,12 @@
-use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::Phi;
+use ::phi::gfx::{Sprite, CopySprite, TextSprite};
 use ::phi::data::Rectangle;
+use ::sdl2::pixels::Color;
 use ::sdl2::render::Renderer;
 
+pub const FONT: &'static str = "belligerent.ttf";
+pub const PIXEL_FONT: &'static str = "arcade.fnt";
+
 #[derive(Clone)]
 pub struct BgSet {
     pub bg_back: Background,
@@ -10,63 +15,86 @@ pub struct BgSet {
 }
 
 impl BgSet {
-    pub fn new(renderer: &mut Renderer, bg_path: &'static str, mid_path: &'static str, fg_path: &'static str) -> BgSet {
-        BgSet {
+    pub fn new(phi: &mut Phi, bg_name: &str, mid_name: &str, fg_name: &str) -> Result<BgSet, String> {
+        Ok(BgSet {
             bg_back: Background {
                 pos: 0.,
+                prev_pos: 0.,
                 vel: 20.,
-                sprite: Sprite::load(renderer, bg_path).unwrap(),
+                sprite: phi.texture(bg_name)?,
             },
 
             bg_middle: Background {
                 pos: 0.,
+                prev_pos: 0.,
                 vel: 40.,
-                sprite: Sprite::load(renderer, mid_path).unwrap(),
+                sprite: phi.texture(mid_name)?,
             },
 
             bg_front: Background {
                 pos: 0.,
+                prev_pos: 0.,
                 vel: 60.,
-                sprite: Sprite::load(renderer, fg_path).unwrap(),
+                sprite: phi.texture(fg_name)?,
             },
-        }
+        })
+    }
+
+    pub fn stars(phi: &mut Phi) -> Result<BgSet, String> {
+        BgSet::new(phi, "starBG.png", "starMG.png", "starFG.png")
     }
 
-    pub fn _render(&mut self, renderer: &mut Renderer, elapsed: f64) {
-        self.render_bg(renderer, elapsed);
-        self.render_fg(renderer, elapsed);
+    pub fn update(&mut self, dt: f64) {
+        self.bg_back.update(dt);
+        self.bg_middle.update(dt);
+        self.bg_front.update(dt);
     }
 
-    pub fn render_bg(&mut self, renderer: &mut Renderer, elapsed: f64) {
-        self.bg_back.render(renderer, elapsed);
-        self.bg_middle.render(renderer, elapsed);
+    pub fn _render(&self, renderer: &mut Renderer, alpha: f64) {
+        self.render_bg(renderer, alpha);
+        self.render_fg(renderer, alpha);
     }
 
-    pub fn render_fg(&mut self, renderer: &mut Renderer, elapsed: f64) {
-        self.bg_front.render(renderer, elapsed);
+    pub fn render_bg(&self, renderer: &mut Renderer, alpha: f64) {
+        self.bg_back.render(renderer, alpha);
+        self.bg_middle.render(renderer, alpha);
+    }
+
+    pub fn render_fg(&self, renderer: &mut Renderer, alpha: f64) {
+        self.bg_front.render(renderer, alpha);
     }
 }
 
 #[derive(Clone)]
 pub struct Background {
     pos: f64,
+    prev_pos: f64,
     vel: f64,
     sprite: Sprite,
 }
 
 impl Background {
-    pub fn render(&mut self, renderer: &mut Renderer, elapsed: f64) {
+    pub fn update(&mut self, dt: f64) {
         let size = self.sprite.size();
-        self.pos += self.vel * elapsed;
+        self.prev_pos = self.pos;
+        self.pos += self.vel * dt;
 
         if self.pos > size.0 {
             self.pos -= size.0;
+            self.prev_pos -= size.0;
         }
+    }
 
-        let (win_w, win_h) = renderer.output_size().unwrap();
+    pub fn render(&self, renderer: &mut Renderer, alpha: f64) {
+        let size = self.sprite.size();
+        let pos = self.prev_pos + (self.pos - self.prev_pos) * alpha;
+
+        // the viewport is the logical resolution
+        let viewport = renderer.viewport();
+        let (win_w, win_h) = (viewport.width(), viewport.height());
         let scale = win_h as f64 / size.1;
 
-        let mut physical_left = -self.pos * scale;
+        let mut physical_left = -pos * scale;
 
         while physical_left < win_w as f64 {
             renderer.copy_sprite(&self.sprite, Rectangle {
@@ -81,3 +109,183 @@ impl Background {
     }
 }
 
+
+pub struct Label {
+    idle_sprite: TextSprite,
+    hover_sprite: TextSprite,
+}
+
+impl Label {
+    pub fn new(phi: &mut Phi, text: &'static str) -> Result<Label, String> {
+        Label::with_font(phi, text, PIXEL_FONT)
+    }
+
+    // `font` may be a TrueType or a bitmap font.
+    pub fn with_font(phi: &mut Phi, text: &'static str, font: &str) -> Result<Label, String> {
+        Ok(Label {
+            idle_sprite: phi.str_sprite(text, font, 32, Color::RGB(220, 220, 220))?,
+            hover_sprite: phi.str_sprite(text, font, 38, Color::RGB(255, 255, 255))?,
+        })
+    }
+}
+
+const MENU_LABEL_H: f64 = 50.;
+const MENU_BOX_W: f64 = 360.;
+
+// One rectangle per menu entry, used both to place the labels and to find
+// the entry under the mouse.
+pub fn menu_rows(phi: &Phi, count: usize) -> Vec<Rectangle> {
+    let (win_w, win_h) = phi.output_size();
+    let box_h = count as f64 * MENU_LABEL_H;
+
+    (0..count).map(|i_row| Rectangle {
+        x: (win_w - MENU_BOX_W) / 2.,
+        y: (win_h - box_h) / 2. + MENU_LABEL_H * i_row as f64,
+        w: MENU_BOX_W,
+        h: MENU_LABEL_H,
+    }).collect()
+}
+
+pub fn menu_row_at(phi: &Phi, count: usize, (x, y): (f64, f64)) -> Option<usize> {
+    menu_rows(phi, count).iter().position(|row| row.contains_point(x, y))
+}
+
+pub fn draw_menu(phi: &mut Phi, labels: &[&Label], selected: usize) {
+    let (win_w, win_h) = phi.output_size();
+    let border_width: f64 = 3.;
+    let box_h: f64 = labels.len() as f64 * MENU_LABEL_H;
+    let margin_h: f64 = 10.;
+
+    // border
+    phi.renderer.set_draw_color(Color::RGB(70, 15, 70));
+    phi.renderer.fill_rect(Rectangle {
+        x: (win_w - MENU_BOX_W) / 2. - border_width,
+        y: (win_h - box_h) / 2. - border_width - margin_h,
+        w: MENU_BOX_W + border_width * 2.,
+        h: box_h + border_width * 2. + margin_h * 2.,
+    }.to_sdl().unwrap());
+
+    // menu box
+    phi.renderer.set_draw_color(Color::RGB(140, 30, 140));
+    phi.renderer.fill_rect(Rectangle {
+        x: (win_w - MENU_BOX_W) / 2.,
+        y: (win_h - box_h) / 2. - margin_h,
+        w: MENU_BOX_W,
+        h: box_h + margin_h * 2.,
+    }.to_sdl().unwrap());
+
+    let rows = menu_rows(phi, labels.len());
+
+    for (i_label, (label, row)) in labels.iter().zip(rows).enumerate() {
+        let sprite =
+            if i_label == selected { &label.hover_sprite }
+            else { &label.idle_sprite };
+
+        let (w, h) = sprite.size();
+        phi.renderer.copy_sprite(sprite, Rectangle {
+            x: row.x + (row.w - w) / 2.,
+            y: row.y + (row.h - h) / 2.,
+            w: w,
+            h: h,
+        });
+    }
+}
+
+const TEXT_FIELD_FONT_SIZE: i32 = 28;
+
+// Single-line text input, fed from the text events while they're started
+// with `Events::start_text_input`.
+pub struct TextField {
+    text: String,
+    max_chars: usize,
+    sprite: Option<Sprite>,
+    editing_sprite: Option<Sprite>,
+    blink: f64,
+}
+
+impl TextField {
+    pub fn new(max_chars: usize) -> TextField {
+        TextField {
+            text: String::new(),
+            max_chars: max_chars,
+            sprite: None,
+            editing_sprite: None,
+            blink: 0.,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Returns true when Enter was pressed during the tick.
+    pub fn update(&mut self, phi: &mut Phi, dt: f64) -> bool {
+        let mut changed = false;
+
+        for _ in 0..phi.events.now.text_backspace {
+            changed |= self.text.pop().is_some();
+        }
+
+        for c in phi.events.now.text.chars() {
+            if self.text.chars().count() < self.max_chars {
+                self.text.push(c);
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.blink = 0.;
+            self.sprite =
+                if self.text.is_empty() { None }
+                else { phi.ttf_str_sprite(&self.text, FONT, TEXT_FIELD_FONT_SIZE, Color::RGB(255, 255, 255)).ok() };
+        }
+
+        // an empty composition means the input method is done with it
+        if let Some((editing, _)) = phi.events.now.text_editing.clone() {
+            self.editing_sprite =
+                if editing.is_empty() { None }
+                else { phi.ttf_str_sprite(&editing, FONT, TEXT_FIELD_FONT_SIZE, Color::RGB(160, 160, 160)).ok() };
+        }
+
+        self.blink = (self.blink + dt) % 1.;
+
+        phi.events.now.text_enter
+    }
+
+    pub fn draw(&self, phi: &mut Phi, rect: Rectangle) {
+        let padding: f64 = 8.;
+
+        phi.renderer.set_draw_color(Color::RGB(220, 220, 220));
+        phi.renderer.fill_rect(rect.to_sdl().unwrap());
+        phi.renderer.set_draw_color(Color::RGB(20, 20, 40));
+        phi.renderer.fill_rect(Rectangle {
+            x: rect.x + 2.,
+            y: rect.y + 2.,
+            w: rect.w - 4.,
+            h: rect.h - 4.,
+        }.to_sdl().unwrap());
+
+        let mut x = rect.x + padding;
+
+        for sprite in self.sprite.iter().chain(self.editing_sprite.iter()) {
+            let (w, h) = sprite.size();
+            phi.renderer.copy_sprite(sprite, Rectangle {
+                x: x,
+                y: rect.y + (rect.h - h) / 2.,
+                w: w,
+                h: h,
+            });
+            x += w;
+        }
+
+        if self.blink < 0.5 {
+            phi.renderer.set_draw_color(Color::RGB(255, 255, 255));
+            phi.renderer.fill_rect(Rectangle {
+                x: x + 1.,
+                y: rect.y + padding,
+                w: 2.,
+                h: rect.h - padding * 2.,
+            }.to_sdl().unwrap());
+        }
+    }
+}